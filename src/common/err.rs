@@ -2,14 +2,32 @@ use std::io;
 use std::num::{ParseFloatError, ParseIntError};
 
 use chrono::format::ParseError as ChronoParseError;
+use rowan::TextRange;
+
+use crate::tkn_tree::TomlKind;
 
 pub type TomlResult<T> = Result<T, ParseTomlError>;
 
-#[derive(PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum TomlErrorKind {
-    UnexpectedToken { tkn: String, ln: usize, col: usize },
+    UnexpectedToken {
+        tkn: String,
+        ln: usize,
+        col: usize,
+    },
+    /// A typed accessor was called on a node holding a different kind of value.
+    TypeMismatch {
+        expected: TomlKind,
+        found: TomlKind,
+        range: TextRange,
+    },
+    /// An edit referred to a key path that is not in the document.
+    KeyNotFound(String),
+    /// An edit would create an item at a key path that is already taken.
+    KeyExists(String),
     DateError,
     NumberError,
+    StringError,
     InternalParseError(String),
 }
 
@@ -26,6 +44,10 @@ impl ParseTomlError {
             info: s,
         }
     }
+
+    pub fn kind(&self) -> &TomlErrorKind {
+        &self.kind
+    }
 }
 
 impl From<io::Error> for ParseTomlError {
@@ -40,7 +62,7 @@ impl From<io::Error> for ParseTomlError {
 
 impl From<ParseTomlError> for io::Error {
     fn from(e: ParseTomlError) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, e.info)
+        io::Error::other(e.info)
     }
 }
 
@@ -84,8 +106,15 @@ impl std::fmt::Display for ParseTomlError {
             TomlErrorKind::UnexpectedToken { tkn, ln, col } => {
                 format!("{} at ln {}, col {}", tkn, ln, col)
             }
+            TomlErrorKind::TypeMismatch { found, range, .. } => {
+                format!("{:?} at {:?}", found, range)
+            }
+            TomlErrorKind::KeyNotFound(path) | TomlErrorKind::KeyExists(path) => {
+                format!("`{}`", path)
+            }
             TomlErrorKind::DateError => "an invalid date-time".into(),
             TomlErrorKind::NumberError => "an invalid number".into(),
+            TomlErrorKind::StringError => "an invalid string".into(),
         };
         write!(f, "{}, found {}", self.info, span)
     }
@@ -0,0 +1,339 @@
+use rowan::TextRange;
+
+use super::edit::key_exists;
+use super::tkn_tree::{
+    path::{heading_path, split_key},
+    walk::walk_tokens_non_ws,
+    SyntaxNode, SyntaxNodeExtTrait, TomlDate, TomlKind, ValueExtTrait,
+};
+use crate::common::err::TomlResult;
+
+/// The decoded value of an item, formatting and comments are gone.
+#[derive(Clone, Debug)]
+pub(crate) enum Value {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Date(TomlDate),
+    Array(Vec<Item>),
+    /// The entries of a table in the order they are first defined.
+    Table(Vec<(String, Item)>),
+}
+
+/// A value along with the range of the node that defines it, for keys this is
+/// the whole `KeyValue` and for tables the `Table` including its heading.
+#[derive(Clone, Debug)]
+pub(crate) struct Item {
+    pub(crate) value: Value,
+    pub(crate) range: TextRange,
+}
+
+impl Item {
+    fn table(range: TextRange) -> Self {
+        Self {
+            value: Value::Table(Vec::default()),
+            range,
+        }
+    }
+
+    /// The item at `path`, only tables are looked into.
+    pub(crate) fn get<S: AsRef<str>>(&self, path: &[S]) -> Option<&Item> {
+        match path.split_first() {
+            None => Some(self),
+            Some((first, rest)) => match &self.value {
+                Value::Table(table) => table
+                    .iter()
+                    .find(|(k, _)| k == first.as_ref())
+                    .and_then(|(_, item)| item.get(rest)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Compares the decoded values ignoring ranges and the order of table entries.
+    pub(crate) fn same(&self, other: &Item) -> bool {
+        match (&self.value, &other.value) {
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            // `nan` is the same as `nan` like its hash
+            (Value::Float(a), Value::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Date(a), Value::Date(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same(b))
+            }
+            (Value::Table(a), Value::Table(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(|(key, a)| {
+                        b.iter()
+                            .find(|(k, _)| k == key)
+                            .map(|(_, b)| a.same(b))
+                            .unwrap_or(false)
+                    })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 64 bit FNV-1a, unlike the std hashers its output never changes between
+/// releases or runs.
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Every value starts with a tag and variable length data ends with its
+    /// length so different values never write the same bytes.
+    fn write_str(&mut self, tag: u8, text: &str) {
+        self.write(&[tag]);
+        self.write(text.as_bytes());
+        self.write(&(text.len() as u64).to_le_bytes());
+    }
+}
+
+impl Item {
+    /// A hash of the decoded value that agrees with `same`, table entries are
+    /// hashed in key order.
+    pub(crate) fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+        self.hash_into(&mut hasher);
+        hasher.0
+    }
+
+    fn hash_into(&self, hasher: &mut Fnv) {
+        match &self.value {
+            Value::Bool(b) => hasher.write(&[0, *b as u8]),
+            Value::Integer(int) => {
+                hasher.write(&[1]);
+                hasher.write(&int.to_le_bytes());
+            }
+            Value::Float(float) => {
+                // `0.0 == -0.0` so both hash the same, and so does every `nan`
+                let float = if *float == 0.0 {
+                    0.0_f64
+                } else if float.is_nan() {
+                    f64::NAN
+                } else {
+                    *float
+                };
+                hasher.write(&[2]);
+                hasher.write(&float.to_bits().to_le_bytes());
+            }
+            Value::String(string) => hasher.write_str(3, string),
+            Value::Date(TomlDate::OffsetDateTime(dt)) => {
+                // offset date times are equal when they are the same instant
+                hasher.write(&[4]);
+                hasher.write(&dt.timestamp().to_le_bytes());
+                hasher.write(&dt.timestamp_subsec_nanos().to_le_bytes());
+            }
+            Value::Date(TomlDate::LocalDateTime(dt)) => hasher.write_str(5, &dt.to_string()),
+            Value::Date(TomlDate::LocalDate(date)) => hasher.write_str(6, &date.to_string()),
+            Value::Date(TomlDate::LocalTime(time)) => hasher.write_str(7, &time.to_string()),
+            Value::Array(items) => {
+                hasher.write(&[8]);
+                for item in items {
+                    item.hash_into(hasher);
+                }
+                hasher.write(&(items.len() as u64).to_le_bytes());
+            }
+            Value::Table(entries) => {
+                let mut entries = entries.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                hasher.write(&[9]);
+                for (key, item) in &entries {
+                    hasher.write_str(3, key);
+                    item.hash_into(hasher);
+                }
+                hasher.write(&(entries.len() as u64).to_le_bytes());
+            }
+        }
+    }
+}
+
+/// The range of `node` without the whitespace it starts or ends with.
+pub(crate) fn trimmed_range(node: &SyntaxNode) -> TextRange {
+    let mut tokens = walk_tokens_non_ws(node).filter(|t| t.kind() != TomlKind::EoF);
+    match tokens.next() {
+        Some(first) => {
+            let last = tokens.last().unwrap_or_else(|| first.clone());
+            TextRange::new(first.text_range().start(), last.text_range().end())
+        }
+        None => node.text_range(),
+    }
+}
+
+/// Returns the entry for `key`, an empty table is added if there is none.
+fn entry<'a>(table: &'a mut Vec<(String, Item)>, key: &str, range: TextRange) -> &'a mut Item {
+    let idx = match table.iter().position(|(k, _)| k == key) {
+        Some(idx) => idx,
+        None => {
+            table.push((key.to_string(), Item::table(range)));
+            table.len() - 1
+        }
+    };
+    &mut table[idx].1
+}
+
+/// The entries of the table `item` holds, for arrays of tables the last table.
+fn as_table<'a>(item: &'a mut Item, path: &[String]) -> TomlResult<&'a mut Vec<(String, Item)>> {
+    match &mut item.value {
+        Value::Table(table) => Ok(table),
+        Value::Array(items) => match items.last_mut() {
+            Some(Item {
+                value: Value::Table(table),
+                ..
+            }) => Ok(table),
+            _ => Err(key_exists(path)),
+        },
+        _ => Err(key_exists(path)),
+    }
+}
+
+/// Walks down `path` creating tables that are only implied by it.
+fn table_at<'a>(
+    root: &'a mut Vec<(String, Item)>,
+    path: &[String],
+    range: TextRange,
+) -> TomlResult<&'a mut Vec<(String, Item)>> {
+    let mut table = root;
+    for (i, seg) in path.iter().enumerate() {
+        table = as_table(entry(table, seg, range), &path[..=i])?;
+    }
+    Ok(table)
+}
+
+fn insert_key_value(table: &mut Vec<(String, Item)>, kv: &SyntaxNode) -> TomlResult<()> {
+    let key = kv
+        .children()
+        .find(|n| n.kind() == TomlKind::Key)
+        .map(|k| split_key(&k.token_text()))
+        .unwrap_or_default();
+    let (value, (last, parents)) = match (
+        kv.children().find(|n| n.kind() == TomlKind::Value),
+        key.split_last(),
+    ) {
+        (Some(value), Some(split)) => (value, split),
+        _ => return Ok(()),
+    };
+    let range = trimmed_range(kv);
+    let table = table_at(table, parents, range)?;
+    if table.iter().any(|(k, _)| k == last) {
+        return Err(key_exists(&key));
+    }
+    let value = value_item(&value)?;
+    table.push((
+        last.clone(),
+        Item {
+            value: value.value,
+            range,
+        },
+    ));
+    Ok(())
+}
+
+/// Decodes a `Value` node.
+pub(crate) fn value_item(node: &SyntaxNode) -> TomlResult<Item> {
+    let kind = node
+        .children_with_tokens()
+        .find(|el| el.kind() != TomlKind::Whitespace)
+        .map(|el| el.kind());
+    let value = match kind {
+        Some(TomlKind::Bool) => Value::Bool(node.as_bool()?),
+        Some(TomlKind::Integer) => Value::Integer(node.as_i64()?),
+        Some(TomlKind::Float) => Value::Float(node.as_f64()?),
+        Some(TomlKind::Date) => Value::Date(node.as_datetime()?),
+        Some(TomlKind::Array) => Value::Array(
+            node.as_array()?
+                .iter()
+                .map(value_item)
+                .collect::<TomlResult<_>>()?,
+        ),
+        Some(TomlKind::InlineTable) => {
+            let mut table = Vec::default();
+            for kv in node
+                .descendants()
+                .find(|n| n.kind() == TomlKind::InlineTable)
+                .iter()
+                .flat_map(|t| t.children().filter(|n| n.kind() == TomlKind::KeyValue))
+            {
+                insert_key_value(&mut table, &kv)?;
+            }
+            Value::Table(table)
+        }
+        _ => Value::String(node.as_str()?),
+    };
+    Ok(Item {
+        value,
+        range: trimmed_range(node),
+    })
+}
+
+/// Decodes a whole document into nested tables.
+pub(crate) fn document(root: &SyntaxNode) -> TomlResult<Item> {
+    let mut top = Vec::default();
+    for node in root.children() {
+        match node.kind() {
+            TomlKind::KeyValue => insert_key_value(&mut top, &node)?,
+            TomlKind::Table => {
+                let path = heading_path(&node);
+                let range = trimmed_range(&node);
+                let is_array = node
+                    .first_child()
+                    .and_then(|h| h.first_child())
+                    .map(|n| n.kind())
+                    == Some(TomlKind::ArrayHeading);
+
+                let table = match path.split_last() {
+                    Some((last, parents)) if is_array => {
+                        let parent = table_at(&mut top, parents, range)?;
+                        let array = match parent.iter().position(|(k, _)| k == last) {
+                            Some(idx) => &mut parent[idx].1,
+                            None => {
+                                let array = Item {
+                                    value: Value::Array(Vec::default()),
+                                    range,
+                                };
+                                parent.push((last.clone(), array));
+                                &mut parent.last_mut().unwrap().1
+                            }
+                        };
+                        match &mut array.value {
+                            Value::Array(items) => {
+                                items.push(Item::table(range));
+                                as_table(items.last_mut().unwrap(), &path)?
+                            }
+                            _ => return Err(key_exists(&path)),
+                        }
+                    }
+                    _ => {
+                        let (last, parents) = match path.split_last() {
+                            Some(split) => split,
+                            None => continue,
+                        };
+                        let parent = table_at(&mut top, parents, range)?;
+                        let item = entry(parent, last, range);
+                        // the heading defines the table, the range of implied
+                        // tables is replaced
+                        item.range = range;
+                        as_table(item, &path)?
+                    }
+                };
+                for kv in node.children().filter(|n| n.kind() == TomlKind::KeyValue) {
+                    insert_key_value(table, &kv)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Item {
+        value: Value::Table(top),
+        range: root.text_range(),
+    })
+}
@@ -0,0 +1,322 @@
+use rowan::{TextRange, TextSize};
+
+use super::doc::{document, Item, Value};
+use super::edit::{
+    apply_edits, insert_array_table, insert_raw, insert_table, is_array_table, key_text,
+    parse_value, quote_key, remove_key_value, remove_table, replace_value, trailing_ws,
+    with_trailing_ws, TextEdit,
+};
+use super::tkn_tree::{
+    path::{heading_path, key_path, split_key},
+    SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind,
+};
+use super::{diff, KeySegment};
+use crate::common::err::TomlResult;
+
+/// An item both sides changed in different ways, the merged document keeps
+/// our side. `ours` and `theirs` are the text of the item on each side, `None`
+/// when that side removed it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub path: Vec<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+/// The result of a three-way merge.
+#[derive(Clone, Debug)]
+pub struct Merged {
+    pub root: SyntaxNode,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Merged {
+    /// The merged text with each conflict written out between `<<<<<<< ours`,
+    /// `=======` and `>>>>>>> theirs` markers in place of our side of it.
+    ///
+    /// The result is not valid toml while there are conflicts.
+    pub fn with_conflict_markers(&self) -> TomlResult<String> {
+        let text = self.root.token_text();
+        let doc = document(&self.root)?;
+
+        let mut edits = Vec::<TextEdit>::default();
+        for conflict in &self.conflicts {
+            let block = format!(
+                "<<<<<<< ours\n{}=======\n{}>>>>>>> theirs",
+                side(&conflict.ours),
+                side(&conflict.theirs)
+            );
+            let edit = match doc.get(&conflict.path) {
+                Some(item) => TextEdit {
+                    range: item.range,
+                    replacement: block,
+                },
+                None => {
+                    // our side is gone, the markers go after what is left of its parent
+                    let end = (0..conflict.path.len())
+                        .rev()
+                        .find_map(|len| doc.get(&conflict.path[..len]))
+                        .filter(|item| item.range != doc.range)
+                        .map(|item| item.range.end())
+                        .unwrap_or_else(|| TextSize::from(text.trim_end().len() as u32));
+                    TextEdit {
+                        range: TextRange::empty(end),
+                        replacement: format!("\n{}", block),
+                    }
+                }
+            };
+            // edits starting at the same offset overlap even when empty, the
+            // markers of conflicts whose side is gone from the same place are
+            // written one after the other
+            let overlap = edits.iter_mut().find(|e| {
+                e.range.start() == edit.range.start()
+                    || (e.range.start() < edit.range.end() && edit.range.start() < e.range.end())
+            });
+            match overlap {
+                Some(e) if e.range.is_empty() && edit.range.is_empty() => {
+                    if !e.replacement.contains(&edit.replacement) {
+                        e.replacement.push_str(&edit.replacement);
+                    }
+                }
+                Some(_) => {}
+                None => edits.push(edit),
+            }
+        }
+        Ok(apply_edits(&text, &edits))
+    }
+}
+
+fn side(text: &Option<String>) -> String {
+    match text {
+        Some(text) => format!("{}\n", text),
+        None => String::default(),
+    }
+}
+
+pub(super) fn starts_with<T: PartialEq>(path: &[T], prefix: &[T]) -> bool {
+    path.len() >= prefix.len() && path.iter().zip(prefix).all(|(a, b)| a == b)
+}
+
+/// The paths changed between `base` and `side`. Changes inside of an array
+/// are changes of the whole array, arrays are merged as a single value.
+fn changed_paths(base: &SyntaxNode, side: &SyntaxNode) -> TomlResult<Vec<Vec<String>>> {
+    let mut paths = Vec::<Vec<String>>::default();
+    for difference in diff(base, side)? {
+        let path = difference
+            .path
+            .iter()
+            .take_while(|seg| matches!(seg, KeySegment::Key(_)))
+            .map(|seg| seg.to_string())
+            .collect::<Vec<_>>();
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+fn item_text(text: &str, doc: &Item, path: &[String]) -> Option<String> {
+    doc.get(path).map(|item| text[item.range].to_string())
+}
+
+fn same(a: Option<&Item>, b: Option<&Item>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.same(b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// The key value pairs that define the item at `path` itself, and not one
+/// of the items inside of it.
+pub(super) fn defining_key_values(root: &SyntaxNode, path: &[String]) -> Vec<SyntaxNode> {
+    root.descendants()
+        .filter(|n| n.kind() == TomlKind::KeyValue)
+        .filter(|kv| {
+            let full = key_path(kv);
+            let own = split_key(&key_text(kv)).len();
+            starts_with(&full, path) && full.len() - own < path.len()
+        })
+        .collect()
+}
+
+pub(super) fn tables_under(root: &SyntaxNode, path: &[String]) -> Vec<SyntaxNode> {
+    root.children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .filter(|t| starts_with(&heading_path(t), path))
+        .collect()
+}
+
+/// Removes everything at `path` from `root`.
+pub(super) fn remove_path(mut root: SyntaxNode, path: &[String]) -> TomlResult<SyntaxNode> {
+    while let Some(kv) = defining_key_values(&root, path).first() {
+        root = remove_key_value(&root, &key_path(kv), false)?;
+    }
+    while let Some(table) = tables_under(&root, path).first() {
+        root = remove_table(&root, &heading_path(table))?;
+    }
+    Ok(root)
+}
+
+/// Inserts `raw_key = value` into the table at `container`. A table that is
+/// only defined by dotted keys gets a longer dotted key in the table holding
+/// them, other missing tables are added with a heading.
+pub(super) fn insert_at(
+    root: &SyntaxNode,
+    container: &[String],
+    raw_key: &str,
+    value: &SyntaxNode,
+) -> TomlResult<SyntaxNode> {
+    if let Ok(root) = insert_raw(root, container, raw_key, value, None) {
+        return Ok(root);
+    }
+    for len in (0..container.len()).rev() {
+        let dotted = root
+            .descendants()
+            .filter(|n| n.kind() == TomlKind::KeyValue)
+            .any(|kv| {
+                let full = key_path(&kv);
+                let own = split_key(&key_text(&kv)).len();
+                own > 1 && full.len() - own == len && starts_with(&full, &container[..=len])
+            });
+        if dotted {
+            let raw_key = container[len..]
+                .iter()
+                .map(|seg| quote_key(seg))
+                .chain(Some(raw_key.to_string()))
+                .collect::<Vec<_>>()
+                .join(".");
+            return insert_raw(root, &container[..len], &raw_key, value, None);
+        }
+    }
+    let root = insert_table(root, container, None)?;
+    insert_raw(&root, container, raw_key, value, None)
+}
+
+/// Adds `table` from another document after the tables of `root`, or after
+/// the last entry when it is part of an array of tables.
+pub(super) fn add_table(root: &SyntaxNode, table: &SyntaxNode) -> TomlResult<SyntaxNode> {
+    let heading = heading_path(table);
+    let root = if is_array_table(table) {
+        insert_array_table(root, &heading, None)?
+    } else {
+        insert_table(root, &heading, None)?
+    };
+
+    // swap the empty table that was just placed for the real one
+    let placeholder = root
+        .children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .filter(|t| heading_path(t) == heading)
+        .last()
+        .unwrap();
+    Ok(replace_table(&placeholder, table))
+}
+
+/// Puts `table` where `old` is, keeping the whitespace `old` ends with.
+pub(super) fn replace_table(old: &SyntaxNode, table: &SyntaxNode) -> SyntaxNode {
+    let ws = trailing_ws(&SyntaxElement::Node(old.clone()));
+    let green = with_trailing_ws(&SyntaxElement::Node(table.clone()), &ws);
+    SyntaxNode::new_root(old.replace_with(green.into_node().unwrap()))
+}
+
+/// Copies everything at `path` in `from` into `root`, the items keep the
+/// formatting they have in `from`.
+pub(super) fn add_path(
+    mut root: SyntaxNode,
+    from: &SyntaxNode,
+    path: &[String],
+) -> TomlResult<SyntaxNode> {
+    for kv in defining_key_values(from, path) {
+        let own = split_key(&key_text(&kv)).len();
+        let full = key_path(&kv);
+        let value = kv.children().find(|n| n.kind() == TomlKind::Value).unwrap();
+        // a value node of an inline table owns its leading whitespace
+        let value = parse_value(&value.token_text())?;
+        root = insert_at(&root, &full[..full.len() - own], &key_text(&kv), &value)?;
+    }
+    for table in tables_under(from, path) {
+        root = add_table(&root, &table)?;
+    }
+    Ok(root)
+}
+
+/// Merges the changes made between `base` and `theirs` into `ours`.
+///
+/// Changes to different keys and tables are merged automatically, our
+/// formatting is kept and the items taken from `theirs` keep theirs. When both
+/// sides changed the same item, or one changed something the other removed, the
+/// item is reported as a `Conflict` unless both ended up with the same value.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{merge, parse_it, SyntaxNodeExtTrait};
+///
+/// let base = parse_it("[package]\nname = \"a\"\nversion = \"0.1.0\"\n").unwrap().syntax();
+/// let ours = parse_it("[package]\nname = \"a\"\nversion = \"0.2.0\"\n").unwrap().syntax();
+/// let theirs = parse_it("[package]\nname = \"a\"\nversion = \"0.1.0\"\nedition = \"2018\"\n")
+///     .unwrap()
+///     .syntax();
+///
+/// let merged = merge(&base, &ours, &theirs).unwrap();
+/// assert!(merged.conflicts.is_empty());
+/// assert_eq!(
+///     merged.root.token_text(),
+///     "[package]\nname = \"a\"\nversion = \"0.2.0\"\nedition = \"2018\"\n"
+/// );
+/// ```
+pub fn merge(base: &SyntaxNode, ours: &SyntaxNode, theirs: &SyntaxNode) -> TomlResult<Merged> {
+    let ours_changed = changed_paths(base, ours)?;
+    let theirs_changed = changed_paths(base, theirs)?;
+    let (base_doc, ours_doc, theirs_doc) = (document(base)?, document(ours)?, document(theirs)?);
+    let (ours_text, theirs_text) = (ours.token_text(), theirs.token_text());
+
+    let mut root = ours.clone();
+    let mut conflicts = Vec::default();
+    for path in theirs_changed {
+        let overlap = ours_changed
+            .iter()
+            .find(|p| starts_with(p, &path) || starts_with(&path, p));
+        if let Some(ours_path) = overlap {
+            // the conflict covers whichever of the two changes is larger
+            let path = if ours_path.len() < path.len() {
+                ours_path.clone()
+            } else {
+                path
+            };
+            // every change of theirs under one of ours widens to the same path
+            let reported = conflicts.iter().any(|c: &Conflict| c.path == path);
+            if !reported && !same(ours_doc.get(&path), theirs_doc.get(&path)) {
+                conflicts.push(Conflict {
+                    ours: item_text(&ours_text, &ours_doc, &path),
+                    theirs: item_text(&theirs_text, &theirs_doc, &path),
+                    path,
+                });
+            }
+            continue;
+        }
+
+        let is_value = |doc: &Item| {
+            doc.get(&path).map(|item| match &item.value {
+                Value::Table(_) => false,
+                Value::Array(items) => !items
+                    .iter()
+                    .any(|item| matches!(item.value, Value::Table(_))),
+                _ => true,
+            }) == Some(true)
+        };
+        let value = defining_key_values(theirs, &path)
+            .first()
+            .and_then(|kv| kv.children().find(|n| n.kind() == TomlKind::Value));
+        root = match value {
+            Some(value) if is_value(&base_doc) && is_value(&theirs_doc) => {
+                replace_value(&root, &path, value.token_text().trim())?
+            }
+            _ => {
+                let removed = remove_path(root, &path)?;
+                add_path(removed, theirs, &path)?
+            }
+        };
+    }
+    Ok(Merged { root, conflicts })
+}
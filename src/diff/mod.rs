@@ -0,0 +1,203 @@
+//! Structural comparison of toml documents.
+//!
+//! Documents are decoded into their tables, keys and values before they are
+//! compared so whitespace, comments, quoting and the order of keys do not
+//! matter, only what the document means.
+
+use std::fmt;
+
+use rowan::TextRange;
+
+use super::common::err::TomlResult;
+use super::edit::{self, common_pairs};
+use super::tkn_tree::{self, SyntaxNode};
+
+pub(crate) mod doc;
+mod merge;
+mod overlay;
+
+use doc::{document, Item, Value};
+pub use merge::{merge, Conflict, Merged};
+pub use overlay::{overlay, ArrayStrategy, Layer, Overlaid};
+
+/// One step of a path into a document, a table key or an index into an array.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeySegment {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for KeySegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySegment::Key(key) => write!(f, "{}", key),
+            KeySegment::Index(idx) => write!(f, "[{}]", idx),
+        }
+    }
+}
+
+/// What happened to an item between two documents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A single difference between two documents, `old` and `new` are the ranges
+/// of the item in each document, `None` when it does not exist there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    pub kind: DiffKind,
+    pub path: Vec<KeySegment>,
+    pub old: Option<TextRange>,
+    pub new: Option<TextRange>,
+}
+
+struct Differ {
+    diffs: Vec<Difference>,
+}
+
+impl Differ {
+    fn push(
+        &mut self,
+        kind: DiffKind,
+        path: &[KeySegment],
+        old: Option<&Item>,
+        new: Option<&Item>,
+    ) {
+        self.diffs.push(Difference {
+            kind,
+            path: path.to_vec(),
+            old: old.map(|i| i.range),
+            new: new.map(|i| i.range),
+        });
+    }
+
+    fn items(&mut self, path: &mut Vec<KeySegment>, old: &Item, new: &Item) {
+        match (&old.value, &new.value) {
+            (Value::Table(a), Value::Table(b)) => self.tables(path, a, b),
+            (Value::Array(a), Value::Array(b)) => self.arrays(path, a, b),
+            _ if old.same(new) => {}
+            _ => self.push(DiffKind::Changed, path, Some(old), Some(new)),
+        }
+    }
+
+    fn tables(
+        &mut self,
+        path: &mut Vec<KeySegment>,
+        old: &[(String, Item)],
+        new: &[(String, Item)],
+    ) {
+        for (key, item) in old {
+            path.push(KeySegment::Key(key.clone()));
+            match new.iter().find(|(k, _)| k == key) {
+                Some((_, new_item)) => self.items(path, item, new_item),
+                None => self.push(DiffKind::Removed, path, Some(item), None),
+            }
+            path.pop();
+        }
+        for (key, item) in new {
+            if !old.iter().any(|(k, _)| k == key) {
+                path.push(KeySegment::Key(key.clone()));
+                self.push(DiffKind::Added, path, None, Some(item));
+                path.pop();
+            }
+        }
+    }
+
+    /// Items that are the same in both arrays are matched up first, what is left
+    /// between them is compared by position.
+    fn arrays(&mut self, path: &mut Vec<KeySegment>, old: &[Item], new: &[Item]) {
+        struct Same<'a>(&'a Item);
+        impl PartialEq for Same<'_> {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.same(other.0)
+            }
+        }
+        let a = old.iter().map(Same).collect::<Vec<_>>();
+        let b = new.iter().map(Same).collect::<Vec<_>>();
+
+        let (mut i, mut j) = (0, 0);
+        let ends = common_pairs(&a, &b)
+            .into_iter()
+            .chain(Some((old.len(), new.len())));
+        for (x, y) in ends {
+            let paired = (x - i).min(y - j);
+            for k in 0..paired {
+                path.push(KeySegment::Index(j + k));
+                self.items(path, &old[i + k], &new[j + k]);
+                path.pop();
+            }
+            for (k, item) in old.iter().enumerate().take(x).skip(i + paired) {
+                path.push(KeySegment::Index(k));
+                self.push(DiffKind::Removed, path, Some(item), None);
+                path.pop();
+            }
+            for (k, item) in new.iter().enumerate().take(y).skip(j + paired) {
+                path.push(KeySegment::Index(k));
+                self.push(DiffKind::Added, path, None, Some(item));
+                path.pop();
+            }
+            i = x + 1;
+            j = y + 1;
+        }
+    }
+}
+
+/// Compares two documents by what they mean rather than how they are written.
+///
+/// Keys and tables are matched by their key path, array items are matched by
+/// value so inserting an item reports a single addition. Containers are never
+/// reported as `Changed`, the items in them that differ are. Indices in a path
+/// are those of the new document unless the item was removed.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{diff, parse_it, DiffKind, KeySegment};
+///
+/// let old = parse_it("[package]\nname = \"a\"\nversion = \"0.1.0\"\n").unwrap().syntax();
+/// let new = parse_it("[package]\nversion   =   \"0.2.0\"\nname = 'a'\n").unwrap().syntax();
+///
+/// let diffs = diff(&old, &new).unwrap();
+/// assert_eq!(diffs.len(), 1);
+/// assert_eq!(diffs[0].kind, DiffKind::Changed);
+/// assert_eq!(
+///     diffs[0].path,
+///     vec![KeySegment::Key("package".into()), KeySegment::Key("version".into())]
+/// );
+/// ```
+pub fn diff(old: &SyntaxNode, new: &SyntaxNode) -> TomlResult<Vec<Difference>> {
+    let mut differ = Differ {
+        diffs: Vec::default(),
+    };
+    differ.items(&mut Vec::default(), &document(old)?, &document(new)?);
+    Ok(differ.diffs)
+}
+
+/// Whether two documents hold the same data.
+///
+/// Whitespace, comments, the order of keys, quoting and whether a table is
+/// written with a heading, inline or with dotted keys are all ignored, only the
+/// decoded values are compared. Arrays are still ordered.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, semantic_eq};
+///
+/// let a = parse_it("[a]\nb = 1\nc = 'x' # a comment\n").unwrap().syntax();
+/// let b = parse_it("a = { c = \"x\", b = 1 }").unwrap().syntax();
+/// assert!(semantic_eq(&a, &b).unwrap());
+/// ```
+pub fn semantic_eq(a: &SyntaxNode, b: &SyntaxNode) -> TomlResult<bool> {
+    Ok(document(a)?.same(&document(b)?))
+}
+
+/// A hash of the data a document holds, documents that are `semantic_eq` have
+/// the same hash.
+///
+/// The hash does not depend on the platform or the run so it can be stored
+/// and compared later.
+pub fn content_hash(root: &SyntaxNode) -> TomlResult<u64> {
+    Ok(document(root)?.content_hash())
+}
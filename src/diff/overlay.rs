@@ -0,0 +1,485 @@
+use std::collections::BTreeMap;
+
+use rowan::{Direction, TextRange};
+
+use super::doc::{document, trimmed_range, value_item, Item, Value};
+use super::edit::{
+    apply_edits, green_element, insert_array_table, is_array_table, replace_children,
+    replace_value, trailing_ws, with_trailing_ws, TextEdit,
+};
+use super::merge::{add_path, defining_key_values, remove_path, starts_with};
+use super::tkn_tree::{
+    parse_it,
+    path::{heading_path, key_path, split_raw},
+    SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind,
+};
+use super::KeySegment;
+use crate::common::err::TomlResult;
+
+/// How an array in the overlay is combined with the same array in the base.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArrayStrategy {
+    /// The overlay's array is used as is.
+    Replace,
+    /// The overlay's items are added after the base's items.
+    Append,
+    /// Items of the overlay replace the base's items they match and the rest
+    /// are added at the end. Tables match when the field named by the key is
+    /// the same in both, any other items when they are the same value.
+    UnionBy(String),
+}
+
+/// The document a value of an overlaid document came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Base,
+    Overlay,
+}
+
+/// The result of applying an overlay.
+#[derive(Clone, Debug)]
+pub struct Overlaid {
+    pub root: SyntaxNode,
+    /// The layer of every value in the result, keyed by its path. Values are
+    /// scalars, array items and empty tables or arrays.
+    pub provenance: BTreeMap<Vec<KeySegment>, Layer>,
+}
+
+fn keys(path: &[String]) -> Vec<KeySegment> {
+    path.iter().cloned().map(KeySegment::Key).collect()
+}
+
+fn is_value(item: &Item) -> bool {
+    match &item.value {
+        Value::Table(_) => false,
+        Value::Array(items) => !items.iter().any(|i| matches!(i.value, Value::Table(_))),
+        _ => true,
+    }
+}
+
+/// The `Value` node the item at `path` is written with, `None` for tables and
+/// arrays of tables.
+fn value_node(root: &SyntaxNode, path: &[String]) -> Option<SyntaxNode> {
+    defining_key_values(root, path)
+        .into_iter()
+        .find(|kv| key_path(kv).len() == path.len())
+        .and_then(|kv| kv.children().find(|n| n.kind() == TomlKind::Value))
+}
+
+/// The `[[path]]` tables of `root` in order.
+fn array_tables(root: &SyntaxNode, path: &[String]) -> Vec<SyntaxNode> {
+    root.children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .filter(|t| is_array_table(t) && heading_path(t) == path)
+        .collect()
+}
+
+struct Overlay<'a> {
+    strategy: &'a ArrayStrategy,
+    layer: &'a SyntaxNode,
+    layer_text: String,
+    layer_doc: Item,
+    from_layer: Vec<Vec<KeySegment>>,
+}
+
+impl Overlay<'_> {
+    fn matches(&self, base: &Item, layer: &Item) -> bool {
+        match (self.strategy, &base.value, &layer.value) {
+            (ArrayStrategy::UnionBy(key), Value::Table(_), Value::Table(_)) => {
+                match (base.get(&[key]), layer.get(&[key])) {
+                    (Some(a), Some(b)) => a.same(b),
+                    _ => base.same(layer),
+                }
+            }
+            _ => base.same(layer),
+        }
+    }
+
+    /// Overlays the table at `path`, `base` is that table as decoded before any
+    /// of it was changed. Overlaying one key leaves the others as they were so
+    /// the base is only decoded once.
+    fn table(
+        &mut self,
+        mut root: SyntaxNode,
+        path: &[String],
+        base_table: &Item,
+    ) -> TomlResult<SyntaxNode> {
+        let entries = match self.layer_doc.get(path).map(|i| &i.value) {
+            Some(Value::Table(entries)) => entries.clone(),
+            _ => return Ok(root),
+        };
+        for (key, item) in entries {
+            let mut full = path.to_vec();
+            full.push(key);
+
+            root = match base_table.get(&full[path.len()..]) {
+                None => {
+                    self.from_layer.push(keys(&full));
+                    add_path(root, self.layer, &full)?
+                }
+                Some(base) => match (&base.value, &item.value) {
+                    (Value::Table(_), Value::Table(_)) => self.table(root, &full, base)?,
+                    (Value::Array(a), Value::Array(b))
+                        if *self.strategy != ArrayStrategy::Replace =>
+                    {
+                        self.array(root, &full, a, b)?
+                    }
+                    _ => {
+                        self.from_layer.push(keys(&full));
+                        let layer_value = value_node(self.layer, &full);
+                        match layer_value {
+                            _ if base.same(&item) => root,
+                            Some(value) if is_value(base) && is_value(&item) => {
+                                replace_value(&root, &full, value.token_text().trim())?
+                            }
+                            _ => add_path(remove_path(root, &full)?, self.layer, &full)?,
+                        }
+                    }
+                },
+            };
+        }
+        Ok(root)
+    }
+
+    /// Appends the overlay's items or merges them by key, the base's items
+    /// that stay keep their place and formatting.
+    fn array(
+        &mut self,
+        root: SyntaxNode,
+        path: &[String],
+        base: &[Item],
+        layer: &[Item],
+    ) -> TomlResult<SyntaxNode> {
+        let mut replaced = Vec::<(usize, usize)>::default();
+        let mut appended = Vec::<usize>::default();
+        for (i, item) in layer.iter().enumerate() {
+            let matched = match self.strategy {
+                ArrayStrategy::UnionBy(_) => base.iter().enumerate().position(|(j, b)| {
+                    !replaced.iter().any(|(r, _)| *r == j) && self.matches(b, item)
+                }),
+                _ => None,
+            };
+            match matched {
+                Some(j) => replaced.push((j, i)),
+                None => appended.push(i),
+            }
+        }
+
+        let mut full = keys(path);
+        // matched tables are overlaid key by key, only their items are marked
+        for (j, _) in replaced.iter().filter(|(j, _)| is_value(&base[*j])) {
+            full.push(KeySegment::Index(*j));
+            self.from_layer.push(full.clone());
+            full.pop();
+        }
+        for k in 0..appended.len() {
+            full.push(KeySegment::Index(base.len() + k));
+            self.from_layer.push(full.clone());
+            full.pop();
+        }
+
+        match (value_node(&root, path), value_node(self.layer, path)) {
+            (Some(value), Some(_)) => {
+                // earlier keys may have moved the array since `base` was decoded
+                let ranges = match value_item(&value)?.value {
+                    Value::Array(items) => items.iter().map(|i| i.range).collect::<Vec<_>>(),
+                    _ => unreachable!("the base item is an array"),
+                };
+                let range = trimmed_range(&value);
+                let text = value.token_text();
+                let offset = range.start() - value.text_range().start();
+                let old = &text[TextRange::at(offset, range.len())];
+                let at = |r: TextRange| r - range.start();
+                let layer_item = |i: usize| self.layer_text[layer[i].range].to_string();
+
+                let mut edits = replaced
+                    .iter()
+                    .filter(|(j, i)| !base[*j].same(&layer[*i]))
+                    .map(|(j, i)| TextEdit {
+                        range: at(ranges[*j]),
+                        replacement: layer_item(*i),
+                    })
+                    .collect::<Vec<_>>();
+                if let Some(last) = ranges.last() {
+                    let end = at(*last).end();
+                    let sep = if old.contains('\n') {
+                        let before = &old[..usize::from(end)];
+                        let line = &before[before.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+                        let indent = line.len() - line.trim_start().len();
+                        format!(",\n{}", &line[..indent])
+                    } else {
+                        ", ".to_string()
+                    };
+                    edits.push(TextEdit {
+                        range: TextRange::empty(end),
+                        replacement: appended
+                            .iter()
+                            .map(|i| format!("{}{}", sep, layer_item(*i)))
+                            .collect(),
+                    });
+                } else {
+                    // nothing to keep, the overlay's array is taken as written
+                    let layer_value = value_node(self.layer, path).unwrap();
+                    edits.push(TextEdit {
+                        range: TextRange::up_to(range.len()),
+                        replacement: layer_value.token_text().trim().to_string(),
+                    });
+                }
+                replace_value(&root, path, &apply_edits(old, &edits))
+            }
+            (None, None) => {
+                let layer_tables = array_tables(self.layer, path);
+                let mut root = root;
+                for (j, i) in replaced {
+                    if !base[j].same(&layer[i]) {
+                        let old = entry_tables(&array_tables(&root, path)[j]);
+                        let layer_entry = entry_tables(&layer_tables[i]);
+                        let tables = self.array_table(&old, &layer_entry, &full, j)?;
+                        root = replace_tables(&old, &tables);
+                    }
+                }
+                for i in appended {
+                    root = insert_array_table(&root, path, None)?;
+                    // the empty entry that was just placed
+                    let placeholder = array_tables(&root, path).pop().unwrap();
+                    root = replace_tables(&[placeholder], &entry_tables(&layer_tables[i]));
+                }
+                Ok(root)
+            }
+            // written inline on one side and as tables on the other
+            _ => {
+                self.from_layer.push(keys(path));
+                add_path(remove_path(root, path)?, self.layer, path)
+            }
+        }
+    }
+
+    /// Overlays the entry `layer` of an array of tables onto the matching
+    /// entry `base`, both given with their sub-tables as `entry_tables` returns
+    /// them. Each entry is treated as a document of its own with the sub-tables
+    /// relative to it, the merged entry and sub-tables are returned.
+    fn array_table(
+        &mut self,
+        base: &[SyntaxNode],
+        layer: &[SyntaxNode],
+        path: &[KeySegment],
+        idx: usize,
+    ) -> TomlResult<Vec<SyntaxNode>> {
+        let depth = heading_segments(&base[0]).len();
+        let body = |tables: &[SyntaxNode]| {
+            let entry = tables[0].token_text();
+            let heading = heading_len(&tables[0]);
+            let mut text = entry[heading..].to_string();
+            for sub in &tables[1..] {
+                text.push_str(&with_heading(sub, |segs| segs[depth..].to_vec()));
+            }
+            text
+        };
+        let base_root = parse_it(&body(base))?.syntax();
+        let layer_root = parse_it(&body(layer))?.syntax();
+
+        let mut inner = Overlay {
+            strategy: self.strategy,
+            layer: &layer_root,
+            layer_text: layer_root.token_text(),
+            layer_doc: document(&layer_root)?,
+            from_layer: Vec::default(),
+        };
+        let merged = inner.table(base_root.clone(), &[], &document(&base_root)?)?;
+
+        let mut prefix = path.to_vec();
+        prefix.push(KeySegment::Index(idx));
+        self.from_layer
+            .extend(inner.from_layer.into_iter().map(|p| {
+                let mut full = prefix.clone();
+                full.extend(p);
+                full
+            }));
+
+        // the sub-tables get the entry's heading back in front of theirs
+        let entry_heading = heading_segments(&base[0]);
+        let mut text = base[0].token_text()[..heading_len(&base[0])].to_string();
+        for el in merged.children_with_tokens() {
+            match el.as_node() {
+                Some(table) if table.kind() == TomlKind::Table => {
+                    text.push_str(&with_heading(table, |segs| {
+                        entry_heading.iter().chain(segs).cloned().collect()
+                    }));
+                }
+                Some(node) => text.push_str(&node.token_text()),
+                None => text.push_str(el.as_token().unwrap().text()),
+            }
+        }
+        let root = parse_it(&text)?.syntax();
+        Ok(root
+            .children()
+            .filter(|n| n.kind() == TomlKind::Table)
+            .collect())
+    }
+}
+
+/// The length of the heading of `table`, the whitespace after it included.
+fn heading_len(table: &SyntaxNode) -> usize {
+    usize::from(table.first_child().unwrap().text_range().len())
+}
+
+/// The segments of the heading of `table` as they are written.
+fn heading_segments(table: &SyntaxNode) -> Vec<String> {
+    let heading = table.first_child().unwrap().token_text();
+    let brackets = if is_array_table(table) { 2 } else { 1 };
+    let trimmed = heading.trim();
+    split_raw(&trimmed[brackets..trimmed.len() - brackets])
+        .iter()
+        .map(|seg| seg.trim().to_string())
+        .collect()
+}
+
+/// The text of `table` with the segments of its heading replaced by what
+/// `f` returns for them.
+fn with_heading<F>(table: &SyntaxNode, f: F) -> String
+where
+    F: FnOnce(&[String]) -> Vec<String>,
+{
+    let text = table.token_text();
+    let heading = &text[..heading_len(table)];
+    let ws = &heading[heading.trim_end().len()..];
+    let brackets = if is_array_table(table) { 2 } else { 1 };
+    format!(
+        "{}{}{}{}{}",
+        "[".repeat(brackets),
+        f(&heading_segments(table)).join("."),
+        "]".repeat(brackets),
+        ws,
+        &text[heading.len()..]
+    )
+}
+
+/// The entry `entry` of an array of tables followed by its sub-tables, the
+/// tables under its heading up to the next entry of the same array.
+fn entry_tables(entry: &SyntaxNode) -> Vec<SyntaxNode> {
+    let path = heading_path(entry);
+    let mut tables = vec![entry.clone()];
+    tables.extend(
+        entry
+            .siblings(Direction::Next)
+            .skip(1)
+            .filter(|n| n.kind() == TomlKind::Table)
+            .take_while(|t| !(is_array_table(t) && heading_path(t) == path))
+            .filter(|t| {
+                let sub = heading_path(t);
+                sub.len() > path.len() && starts_with(&sub, &path)
+            }),
+    );
+    tables
+}
+
+/// Puts `tables` where the first of `old` is and removes the rest of `old`.
+/// The last of the new tables keeps the whitespace the last of `old` ends with.
+fn replace_tables(old: &[SyntaxNode], tables: &[SyntaxNode]) -> SyntaxNode {
+    let root = old[0].parent().unwrap();
+    let ws = trailing_ws(&SyntaxElement::Node(old[old.len() - 1].clone()));
+    let mut greens = Vec::default();
+    for el in root.children_with_tokens() {
+        match el.as_node() {
+            Some(n) if n == &old[0] => {
+                for (i, table) in tables.iter().enumerate() {
+                    let table = SyntaxElement::Node(table.clone());
+                    greens.push(if i + 1 == tables.len() {
+                        with_trailing_ws(&table, &ws)
+                    } else {
+                        green_element(&table)
+                    });
+                }
+            }
+            Some(n) if old.contains(n) => {}
+            _ => greens.push(green_element(&el)),
+        }
+    }
+    replace_children(&root, greens)
+}
+
+fn provenance(
+    item: &Item,
+    path: &mut Vec<KeySegment>,
+    from_layer: &[Vec<KeySegment>],
+    map: &mut BTreeMap<Vec<KeySegment>, Layer>,
+) {
+    let leaf = match &item.value {
+        Value::Table(entries) if !entries.is_empty() => {
+            for (key, item) in entries {
+                path.push(KeySegment::Key(key.clone()));
+                provenance(item, path, from_layer, map);
+                path.pop();
+            }
+            false
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (idx, item) in items.iter().enumerate() {
+                path.push(KeySegment::Index(idx));
+                provenance(item, path, from_layer, map);
+                path.pop();
+            }
+            false
+        }
+        _ => true,
+    };
+    if leaf {
+        let layer = if from_layer.iter().any(|p| starts_with(path, p)) {
+            Layer::Overlay
+        } else {
+            Layer::Base
+        };
+        map.insert(path.clone(), layer);
+    }
+}
+
+/// Applies `layer` on top of `base`.
+///
+/// Values the overlay sets replace those of the base, tables are merged key by
+/// key and arrays are combined according to `strategy`. Everything the overlay
+/// does not touch keeps the base's comments and layout, what it adds is written
+/// the way the overlay writes it. When an array is written inline on one side
+/// and as an array of tables on the other the overlay's array replaces it.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{overlay, parse_it, ArrayStrategy, KeySegment, Layer, SyntaxNodeExtTrait};
+///
+/// let base = parse_it("[server]\nport = 80 # default\nhosts = [ \"a\" ]\n").unwrap().syntax();
+/// let prod = parse_it("[server]\nport = 443\nhosts = [ \"b\" ]\n").unwrap().syntax();
+///
+/// let merged = overlay(&base, &prod, &ArrayStrategy::Append).unwrap();
+/// assert_eq!(
+///     merged.root.token_text(),
+///     "[server]\nport = 443 # default\nhosts = [ \"a\", \"b\" ]\n"
+/// );
+///
+/// let path = |key: &str| vec![KeySegment::Key("server".into()), KeySegment::Key(key.into())];
+/// assert_eq!(merged.provenance[&path("port")], Layer::Overlay);
+/// ```
+pub fn overlay(
+    base: &SyntaxNode,
+    layer: &SyntaxNode,
+    strategy: &ArrayStrategy,
+) -> TomlResult<Overlaid> {
+    let mut overlay = Overlay {
+        strategy,
+        layer,
+        layer_text: layer.token_text(),
+        layer_doc: document(layer)?,
+        from_layer: Vec::default(),
+    };
+    let root = overlay.table(base.clone(), &[], &document(base)?)?;
+
+    let mut map = BTreeMap::default();
+    provenance(
+        &document(&root)?,
+        &mut Vec::default(),
+        &overlay.from_layer,
+        &mut map,
+    );
+    Ok(Overlaid {
+        root,
+        provenance: map,
+    })
+}
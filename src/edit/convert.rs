@@ -0,0 +1,377 @@
+use super::insert::{insert_raw, insert_table};
+use super::remove::{remove_key_value, remove_table};
+use super::{
+    attached_comments, comment_node, green_element, key_exists, line_end, line_ending, line_indent,
+    node, not_found, parse_value, quote_key, replace_children,
+};
+use crate::common::err::TomlResult;
+use crate::tkn_tree::{
+    path::{find_key_value, heading_path, key_path, split_key, split_raw},
+    SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind,
+};
+
+/// The ways a table can be written in a toml document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableForm {
+    /// An inline table, `serde = { version = "1" }`.
+    Inline,
+    /// A table with its own heading, `[dependencies.serde]`.
+    Standard,
+    /// Dotted keys in the parent table, `serde.version = "1"`.
+    Dotted,
+}
+
+/// A key value pair of the table being converted, the key is relative to the
+/// table and kept as written.
+struct Entry {
+    key: Vec<String>,
+    value: SyntaxNode,
+}
+
+impl Entry {
+    fn new(key: Vec<String>, value: &SyntaxNode) -> TomlResult<Self> {
+        let value = value
+            .children()
+            .find(|n| n.kind() == TomlKind::Value)
+            .expect("KeyValue has a Value");
+        Ok(Self {
+            key,
+            value: parse_value(&value.token_text())?,
+        })
+    }
+    fn raw_key(&self) -> String {
+        self.key.join(".")
+    }
+}
+
+/// A table found in one of its forms, `container` is the path of the table
+/// the key value pairs of the `Inline` and `Dotted` forms live in.
+struct Found {
+    form: TableForm,
+    container: Vec<String>,
+    entries: Vec<Entry>,
+    comments: Vec<String>,
+}
+
+fn raw_segments(kv: &SyntaxNode) -> Vec<String> {
+    kv.children()
+        .find(|n| n.kind() == TomlKind::Key)
+        .map(|k| {
+            split_raw(&k.token_text())
+                .iter()
+                .map(|s| s.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn comment_text(el: &SyntaxElement) -> String {
+    el.as_node()
+        .map(|n| n.token_text().trim().to_string())
+        .unwrap_or_default()
+}
+
+/// The comments above a key value pair and the one following it on the same line.
+fn key_value_comments(kv: &SyntaxNode) -> Vec<String> {
+    let kids = match kv.parent() {
+        Some(parent) => parent.children_with_tokens().collect::<Vec<_>>(),
+        None => return vec![],
+    };
+    let idx = kids.iter().position(|el| el.as_node() == Some(kv)).unwrap();
+    let start = attached_comments(&kids, idx);
+    let end = line_end(&kids, idx);
+    kids[start..=end]
+        .iter()
+        .filter(|el| el.kind() == TomlKind::Comment)
+        .map(comment_text)
+        .collect()
+}
+
+/// The comments above a table's heading and those inside the table, the
+/// comments at the end of the table that belong to the next heading are left out.
+fn table_comments(root: &SyntaxNode, table: &SyntaxNode) -> Vec<String> {
+    let kids = root.children_with_tokens().collect::<Vec<_>>();
+    let idx = kids
+        .iter()
+        .position(|el| el.as_node() == Some(table))
+        .unwrap();
+    let mut comments = match kids[..idx].last().and_then(|el| el.as_node()) {
+        Some(prev) if prev.kind() == TomlKind::Table => {
+            let prev_kids = prev.children_with_tokens().collect::<Vec<_>>();
+            let start = attached_comments(&prev_kids, prev_kids.len()).max(1);
+            prev_kids[start..].iter().map(comment_text).collect()
+        }
+        _ => {
+            let start = attached_comments(&kids, idx);
+            kids[start..idx]
+                .iter()
+                .map(comment_text)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let table_kids = table.children_with_tokens().collect::<Vec<_>>();
+    let has_next = kids[idx + 1..]
+        .iter()
+        .any(|el| el.kind() == TomlKind::Table);
+    let end = if has_next {
+        attached_comments(&table_kids, table_kids.len()).max(1)
+    } else {
+        table_kids.len()
+    };
+    comments.extend(
+        table_kids[..end]
+            .iter()
+            .filter(|el| el.kind() == TomlKind::Comment)
+            .map(comment_text),
+    );
+    comments
+}
+
+/// The path of the longest table heading that is a proper prefix of `path`,
+/// the root of the document if there is none.
+fn parent_table<S: AsRef<str>>(root: &SyntaxNode, path: &[S]) -> Vec<String> {
+    root.children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .map(|t| heading_path(&t))
+        .filter(|found| {
+            found.len() < path.len() && found.iter().zip(path).all(|(a, b)| a == b.as_ref())
+        })
+        .max_by_key(|found| found.len())
+        .unwrap_or_default()
+}
+
+fn find_table<S: AsRef<str>>(root: &SyntaxNode, path: &[S]) -> TomlResult<Found> {
+    let starts_with = |found: &[String]| {
+        found.len() >= path.len() && found.iter().zip(path).all(|(a, b)| a == b.as_ref())
+    };
+
+    let table = root
+        .children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .find(|t| {
+            let is_array = t
+                .first_child()
+                .and_then(|h| h.first_child())
+                .map(|n| n.kind())
+                == Some(TomlKind::ArrayHeading);
+            heading_path(t).len() == path.len() && starts_with(&heading_path(t)) && !is_array
+        });
+    if let Some(table) = table {
+        // sub-tables have nowhere to go once the heading is gone
+        let sub = root
+            .children()
+            .filter(|n| n.kind() == TomlKind::Table)
+            .map(|t| heading_path(&t))
+            .find(|found| found.len() > path.len() && starts_with(found));
+        if let Some(sub) = sub {
+            return Err(key_exists(&sub));
+        }
+        let entries = table
+            .children()
+            .filter(|n| n.kind() == TomlKind::KeyValue)
+            .map(|kv| Entry::new(raw_segments(&kv), &kv))
+            .collect::<TomlResult<Vec<_>>>()?;
+        return Ok(Found {
+            form: TableForm::Standard,
+            container: parent_table(root, path),
+            entries,
+            comments: table_comments(root, &table),
+        });
+    }
+
+    let inline = find_key_value(root, path).filter(|kv| {
+        kv.children()
+            .find(|n| n.kind() == TomlKind::Value)
+            .and_then(|v| v.first_child())
+            .map(|n| n.kind())
+            == Some(TomlKind::InlineTable)
+    });
+    if let Some(kv) = inline {
+        let container = match kv.parent() {
+            Some(p) if p.kind() == TomlKind::Table => heading_path(&p),
+            Some(p) if p.kind() == TomlKind::Root => vec![],
+            _ => return Err(not_found(path)),
+        };
+        let table = kv.descendants().find(|n| n.kind() == TomlKind::InlineTable);
+        let entries = table
+            .iter()
+            .flat_map(|t| t.children().filter(|n| n.kind() == TomlKind::KeyValue))
+            .map(|kv| Entry::new(raw_segments(&kv), &kv))
+            .collect::<TomlResult<Vec<_>>>()?;
+        return Ok(Found {
+            form: TableForm::Inline,
+            container,
+            entries,
+            comments: key_value_comments(&kv),
+        });
+    }
+
+    let mut found = Found {
+        form: TableForm::Dotted,
+        container: vec![],
+        entries: vec![],
+        comments: vec![],
+    };
+    for kv in root
+        .descendants()
+        .filter(|n| n.kind() == TomlKind::KeyValue)
+    {
+        let full = key_path(&kv);
+        let own = raw_segments(&kv);
+        let prefix = full.len() - own.len();
+        let in_table = kv.parent().map(|p| p.kind()) != Some(TomlKind::InlineTable);
+        if !in_table || prefix >= path.len() || full.len() <= path.len() || !starts_with(&full) {
+            continue;
+        }
+        if found.entries.is_empty() {
+            found.container = full[..prefix].to_vec();
+        }
+        found
+            .entries
+            .push(Entry::new(own[path.len() - prefix..].to_vec(), &kv)?);
+        found.comments.extend(key_value_comments(&kv));
+    }
+    if found.entries.is_empty() {
+        Err(not_found(path))
+    } else {
+        Ok(found)
+    }
+}
+
+/// Inserts `comments` on their own lines right above `target`, a `Table` or
+/// `KeyValue` node.
+fn comments_above(target: &SyntaxNode, comments: &[String]) -> SyntaxNode {
+    let parent = target.parent().expect("target is not the root");
+    let kids = parent.children_with_tokens().collect::<Vec<_>>();
+    let idx = kids
+        .iter()
+        .position(|el| el.as_node() == Some(target))
+        .unwrap();
+    let eol = line_ending(target);
+
+    if target.kind() == TomlKind::Table {
+        if let Some(prev) = kids[..idx]
+            .last()
+            .and_then(|el| el.as_node())
+            .filter(|n| n.kind() == TomlKind::Table)
+        {
+            // the comments above a heading are the last children of the table before it
+            let mut greens = prev
+                .children_with_tokens()
+                .map(|el| green_element(&el))
+                .collect::<Vec<_>>();
+            greens.extend(comments.iter().map(|c| comment_node(c, eol)));
+            let mut root = kids.iter().map(green_element).collect::<Vec<_>>();
+            root[idx - 1] = node(TomlKind::Table, greens);
+            return replace_children(&parent, root);
+        }
+    }
+    let ws = format!("{}{}", eol, line_indent(target));
+    let mut greens = kids.iter().map(green_element).collect::<Vec<_>>();
+    for (i, c) in comments.iter().enumerate() {
+        greens.insert(idx + i, comment_node(c, &ws));
+    }
+    replace_children(&parent, greens)
+}
+
+/// Rewrites the table at `path` in the given `form`, its key value pairs and
+/// comments are kept. Comments are moved above the heading or the first key of
+/// the converted table since inline tables cannot hold them.
+///
+/// A table with its own heading is converted into the table named by the
+/// longest existing heading above it, or into the top level of the document.
+/// Tables with sub-tables can not be converted out of the `Standard` form.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{convert_table, parse_it, SyntaxNodeExtTrait, TableForm};
+///
+/// let toml = "[dependencies]\nchrono = \"0.4\"\nserde = { version = \"1\", features = [ \"derive\" ] }\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let table = convert_table(&root, &["dependencies", "serde"], TableForm::Standard).unwrap();
+/// assert_eq!(
+///     table.token_text(),
+///     "[dependencies]\nchrono = \"0.4\"\n\n[dependencies.serde]\nversion = \"1\"\nfeatures = [ \"derive\" ]\n"
+/// );
+/// ```
+pub fn convert_table<S: AsRef<str>>(
+    root: &SyntaxNode,
+    path: &[S],
+    form: TableForm,
+) -> TomlResult<SyntaxNode> {
+    let found = find_table(root, path)?;
+    if found.form == form {
+        return Ok(root.clone());
+    }
+    let path = path
+        .iter()
+        .map(|s| s.as_ref().to_string())
+        .collect::<Vec<_>>();
+
+    let mut root = match found.form {
+        TableForm::Standard => remove_table(root, &path)?,
+        TableForm::Inline => remove_key_value(root, &path, false)?,
+        TableForm::Dotted => {
+            let mut root = root.clone();
+            for entry in &found.entries {
+                let mut full = path.clone();
+                full.extend(split_key(&entry.raw_key()));
+                root = remove_key_value(&root, &full, false)?;
+            }
+            root
+        }
+    };
+
+    // the key of the table relative to the table it is written in
+    let rel = path[found.container.len()..]
+        .iter()
+        .map(|s| quote_key(s))
+        .collect::<Vec<_>>()
+        .join(".");
+    let first = match form {
+        TableForm::Standard => {
+            root = insert_table(&root, &path, None)?;
+            for entry in &found.entries {
+                root = insert_raw(&root, &path, &entry.raw_key(), &entry.value, None)?;
+            }
+            root.children()
+                .filter(|n| n.kind() == TomlKind::Table)
+                .find(|t| heading_path(t) == path)
+        }
+        TableForm::Inline => {
+            let pairs = found
+                .entries
+                .iter()
+                .map(|e| format!("{} = {}", e.raw_key(), e.value.token_text()))
+                .collect::<Vec<_>>();
+            let value = if pairs.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{ {} }}", pairs.join(", "))
+            };
+            root = insert_raw(&root, &found.container, &rel, &parse_value(&value)?, None)?;
+            find_key_value(&root, &path)
+        }
+        TableForm::Dotted => {
+            for entry in &found.entries {
+                let key = format!("{}.{}", rel, entry.raw_key());
+                root = insert_raw(&root, &found.container, &key, &entry.value, None)?;
+            }
+            let mut first = path.clone();
+            first.extend(
+                found
+                    .entries
+                    .first()
+                    .map(|e| split_key(&e.raw_key()))
+                    .unwrap_or_default(),
+            );
+            find_key_value(&root, &first)
+        }
+    };
+    match first {
+        Some(target) if !found.comments.is_empty() => Ok(comments_above(&target, &found.comments)),
+        _ => Ok(root),
+    }
+}
@@ -0,0 +1,462 @@
+use super::sort::{heading_segments, match_table, Matcher, Pattern};
+use super::{
+    eq_spacing, green_element, heading_node, key_exists, key_text, key_value_kids, key_value_node,
+    line_end, line_ending, line_indent, node, not_found, parse_value, quote_key,
+    rebuild_inline_table, replace_children, trailing_ws, with_trailing_ws, without_leading_ws,
+};
+use crate::common::err::TomlResult;
+use crate::tkn_tree::{
+    path::{find_key_value, heading_path, split_key},
+    SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind,
+};
+
+/// The whitespace around the `=` of a key value inserted next to `neighbour`.
+///
+/// When the key values among `kids` line up their `=` with padding the new
+/// key is padded to the same column, otherwise the neighbour's spacing is
+/// copied.
+fn aligned_eq_spacing(
+    kids: &[SyntaxElement],
+    neighbour: &SyntaxNode,
+    raw_key: &str,
+) -> (String, String) {
+    let (eq_l, eq_r) = eq_spacing(neighbour);
+    if eq_l.is_empty() || eq_l.chars().any(|c| c != ' ') {
+        return (eq_l, eq_r);
+    }
+    let column = |kv: &SyntaxNode| {
+        let (l, _) = eq_spacing(kv);
+        (key_text(kv).chars().count(), l.chars().count())
+    };
+    let (key_width, pad) = column(neighbour);
+    let eq_col = key_width + pad;
+    let aligned = pad > 1
+        || kids
+            .iter()
+            .filter_map(|el| el.as_node())
+            .filter(|n| n.kind() == TomlKind::KeyValue)
+            .map(column)
+            .any(|(w, l)| w != key_width && w + l == eq_col);
+    if aligned {
+        let pad = eq_col.saturating_sub(raw_key.chars().count()).max(1);
+        (" ".repeat(pad), eq_r)
+    } else {
+        (eq_l, eq_r)
+    }
+}
+
+/// Inserts a key value pair into a `Table` or the `Root` node.
+fn insert_into_table(
+    container: &SyntaxNode,
+    raw_key: &str,
+    value: &SyntaxNode,
+    sorted: bool,
+) -> SyntaxNode {
+    let kids = container.children_with_tokens().collect::<Vec<_>>();
+    let kvs = kids
+        .iter()
+        .enumerate()
+        .filter(|(_, el)| el.kind() == TomlKind::KeyValue)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    // the index of the element the new key value follows
+    let anchor = if sorted && !kvs.is_empty() {
+        let before = kvs
+            .iter()
+            .rev()
+            .find(|i| key_text(kids[**i].as_node().unwrap()).as_str() <= raw_key);
+        match before {
+            Some(i) => Some(line_end(&kids, *i)),
+            None => {
+                // goes above the first key value and the comments attached to it
+                let mut first = kvs[0];
+                while first > 0 && kids[first - 1].kind() == TomlKind::Comment {
+                    first -= 1;
+                }
+                first.checked_sub(1)
+            }
+        }
+    } else if let Some(last) = kvs.last() {
+        Some(line_end(&kids, *last))
+    } else {
+        kids.iter().position(|el| el.kind() == TomlKind::Heading)
+    };
+
+    let eol = line_ending(container);
+    let neighbour = anchor
+        .and_then(|a| kids[a].as_node().cloned())
+        .filter(|n| n.kind() == TomlKind::KeyValue)
+        .or_else(|| kvs.first().and_then(|i| kids[*i].as_node().cloned()));
+    let (eq_l, eq_r) = neighbour
+        .as_ref()
+        .map(|n| aligned_eq_spacing(&kids, n, raw_key))
+        .unwrap_or_else(|| (" ".into(), " ".into()));
+    let indent = neighbour.as_ref().map(line_indent).unwrap_or_default();
+
+    let mut greens = kids.iter().map(green_element).collect::<Vec<_>>();
+    match anchor {
+        Some(a) => {
+            let ws = trailing_ws(&kids[a]);
+            let line = format!("{}{}", eol, indent);
+            // when appending the new item takes over whatever whitespace separated
+            // the anchor from the next table
+            let is_last = !kids[a + 1..]
+                .iter()
+                .any(|el| el.kind() == TomlKind::KeyValue);
+            let (anchor_ws, new_ws) = if is_last { (line, ws) } else { (ws, line) };
+            greens[a] = with_trailing_ws(&kids[a], &anchor_ws);
+            greens.insert(
+                a + 1,
+                key_value_node(raw_key, (&eq_l, &eq_r), value, &new_ws),
+            );
+        }
+        None => {
+            let new_ws = if kids.first().map(|el| el.kind()) == Some(TomlKind::Table) {
+                eol.repeat(2)
+            } else {
+                eol.to_string()
+            };
+            greens.insert(0, key_value_node(raw_key, (&eq_l, &eq_r), value, &new_ws));
+        }
+    }
+    replace_children(container, greens)
+}
+
+/// Inserts a key value pair into an `InlineTable` node.
+fn insert_into_inline(
+    table: &SyntaxNode,
+    raw_key: &str,
+    value: &SyntaxNode,
+    sorted: bool,
+) -> SyntaxNode {
+    let kvs = table
+        .children()
+        .filter(|n| n.kind() == TomlKind::KeyValue)
+        .collect::<Vec<_>>();
+    let (eq_l, eq_r) = kvs
+        .first()
+        .map(eq_spacing)
+        .unwrap_or_else(|| (" ".into(), " ".into()));
+
+    let mut entries = kvs
+        .iter()
+        .map(|kv| (key_text(kv), without_leading_ws(kv)))
+        .collect::<Vec<_>>();
+    let idx = if sorted {
+        entries
+            .iter()
+            .position(|(k, _)| k.as_str() > raw_key)
+            .unwrap_or(entries.len())
+    } else {
+        entries.len()
+    };
+    entries.insert(
+        idx,
+        (
+            raw_key.to_string(),
+            key_value_kids(raw_key, (&eq_l, &eq_r), value, ""),
+        ),
+    );
+
+    let green = rebuild_inline_table(table, entries.into_iter().map(|(_, e)| e).collect());
+    SyntaxNode::new_root(table.replace_with(green.into_node().unwrap()))
+}
+
+/// Inserts `key = value` into the table at `path`.
+///
+/// `path` may name a `[table]`, the last entry of an `[[array.of.tables]]` or
+/// a key whose value is an inline table. An empty `path` inserts at the top
+/// level of the document. Indentation, spacing around `=` and line endings are
+/// taken from the neighbouring items.
+///
+/// When a `Matcher` is given and its `heading` matches the table, the key is
+/// placed at its sorted position instead of at the end.
+///
+/// Returns `TomlErrorKind::KeyExists` when that table already has `key`, as a
+/// key, the start of a dotted key or a sub-table.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{insert_key_value, parse_it, Matcher, SyntaxNodeExtTrait};
+///
+/// const MATCHER: Matcher<'static> = Matcher {
+///     heading: &["[dependencies]"],
+///     segmented: &[],
+///     heading_key: &[],
+///     array_table_key: &[],
+/// };
+///
+/// let toml = "[dependencies]\nchrono = \"0.4\"\nrowan = \"0.10.0\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let added = insert_key_value(&root, &["dependencies"], "muncher", "\"0.6\"", Some(&MATCHER))
+///     .unwrap();
+/// assert_eq!(
+///     added.token_text(),
+///     "[dependencies]\nchrono = \"0.4\"\nmuncher = \"0.6\"\nrowan = \"0.10.0\"\n"
+/// );
+/// ```
+pub fn insert_key_value<S: AsRef<str>>(
+    root: &SyntaxNode,
+    path: &[S],
+    key: &str,
+    value: &str,
+    matcher: Option<&Matcher<'_>>,
+) -> TomlResult<SyntaxNode> {
+    let target = find_target(root, path)?;
+    if target.has_key(root, path, key) {
+        let mut full = path
+            .iter()
+            .map(|s| s.as_ref().to_string())
+            .collect::<Vec<_>>();
+        full.push(key.to_string());
+        return Err(key_exists(&full));
+    }
+    let value = parse_value(value)?;
+    Ok(target.insert(&quote_key(key), &value, matcher))
+}
+
+/// The table a key value is inserted into.
+enum Target {
+    /// The `Root` node, for an empty path.
+    Root(SyntaxNode),
+    /// A `[table]` or the last entry of an `[[array.of.tables]]`.
+    Table(SyntaxNode),
+    /// The `InlineTable` node of a key value.
+    Inline(SyntaxNode),
+}
+
+impl Target {
+    /// Whether `key` is taken in this table, by a key value, a dotted key
+    /// starting with it or a sub-table.
+    fn has_key<S: AsRef<str>>(&self, root: &SyntaxNode, path: &[S], key: &str) -> bool {
+        let container = match self {
+            Target::Root(n) | Target::Table(n) | Target::Inline(n) => n,
+        };
+        let in_kvs = container
+            .children()
+            .filter(|n| n.kind() == TomlKind::KeyValue)
+            .any(|kv| split_key(&key_text(&kv)).first().map(String::as_str) == Some(key));
+        if in_kvs {
+            return true;
+        }
+
+        let is_sub_table = |t: &SyntaxNode| {
+            let found = heading_path(t);
+            found.len() > path.len()
+                && found.iter().zip(path).all(|(a, b)| a == b.as_ref())
+                && found[path.len()] == key
+        };
+        let mut tables = root.children().filter(|n| n.kind() == TomlKind::Table);
+        match self {
+            Target::Table(table) if is_array_table(table) => {
+                // the sub-tables of an entry follow it up to the next entry
+                tables
+                    .skip_while(|t| t != table)
+                    .skip(1)
+                    .take_while(|t| !(is_array_table(t) && heading_path(t).len() == path.len()))
+                    .any(|t| is_sub_table(&t))
+            }
+            Target::Inline(_) => false,
+            _ => tables.any(|t| is_sub_table(&t)),
+        }
+    }
+
+    fn insert(
+        &self,
+        raw_key: &str,
+        value: &SyntaxNode,
+        matcher: Option<&Matcher<'_>>,
+    ) -> SyntaxNode {
+        let sorted = |table: Option<SyntaxNode>| match (matcher, table) {
+            (Some(m), Some(t)) => match_table(&t, &Pattern::all(m.heading)),
+            _ => false,
+        };
+        match self {
+            Target::Root(root) => insert_into_table(root, raw_key, value, false),
+            Target::Table(table) => {
+                insert_into_table(table, raw_key, value, sorted(Some(table.clone())))
+            }
+            Target::Inline(inline) => {
+                let table = inline.ancestors().find(|n| n.kind() == TomlKind::Table);
+                insert_into_inline(inline, raw_key, value, sorted(table))
+            }
+        }
+    }
+}
+
+/// Resolves the table at `path`, for arrays of tables this is the last entry.
+fn find_target<S: AsRef<str>>(root: &SyntaxNode, path: &[S]) -> TomlResult<Target> {
+    if path.is_empty() {
+        return Ok(Target::Root(root.clone()));
+    }
+    let table = root
+        .children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .filter(|t| {
+            let found = heading_path(t);
+            found.len() == path.len() && found.iter().zip(path).all(|(a, b)| a == b.as_ref())
+        })
+        .last();
+    if let Some(table) = table {
+        return Ok(Target::Table(table));
+    }
+
+    find_key_value(root, path)
+        .and_then(|kv| kv.children().find(|n| n.kind() == TomlKind::Value))
+        .and_then(|v| v.children().find(|n| n.kind() == TomlKind::InlineTable))
+        .map(Target::Inline)
+        .ok_or_else(|| not_found(path))
+}
+
+/// Inserts a key value pair with the key as written into the table at `path`,
+/// the caller checks that the key is not already taken.
+pub(crate) fn insert_raw<S: AsRef<str>>(
+    root: &SyntaxNode,
+    path: &[S],
+    raw_key: &str,
+    value: &SyntaxNode,
+    matcher: Option<&Matcher<'_>>,
+) -> TomlResult<SyntaxNode> {
+    Ok(find_target(root, path)?.insert(raw_key, value, matcher))
+}
+
+pub(crate) fn is_array_table(table: &SyntaxNode) -> bool {
+    table
+        .first_child()
+        .and_then(|h| h.first_child())
+        .map(|n| n.kind())
+        == Some(TomlKind::ArrayHeading)
+}
+
+fn insert_heading<S: AsRef<str>>(
+    root: &SyntaxNode,
+    path: &[S],
+    array: bool,
+    matcher: Option<&Matcher<'_>>,
+) -> TomlResult<SyntaxNode> {
+    let segs = path
+        .iter()
+        .map(|s| s.as_ref().to_string())
+        .collect::<Vec<_>>();
+    let tables = root
+        .children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .collect::<Vec<_>>();
+    // a table may only be defined once, array of tables may be repeated
+    let taken = tables
+        .iter()
+        .any(|t| heading_path(t) == segs && !(array && is_array_table(t)));
+    if segs.is_empty() || taken || find_key_value(root, &segs).is_some() {
+        return Err(key_exists(&segs));
+    }
+
+    let kids = root.children_with_tokens().collect::<Vec<_>>();
+    let pos_of = |t: &SyntaxNode| kids.iter().position(|el| el.as_node() == Some(t)).unwrap();
+    let heading_text = |t: &SyntaxNode| t.first_child().unwrap().token_text().trim().to_string();
+    let new_heading = format!(
+        "[{}]",
+        segs.iter()
+            .map(|s| quote_key(s))
+            .collect::<Vec<_>>()
+            .join(".")
+    );
+    let last_with_prefix = |prefix: &[String]| {
+        tables
+            .iter()
+            .rev()
+            .find(|t| heading_path(t).starts_with(prefix))
+            .map(pos_of)
+    };
+    let seg_match = matcher.and_then(|m| {
+        Pattern::all(m.segmented)
+            .into_iter()
+            .find(|seg| seg.is_segment_match(&new_heading))
+    });
+
+    // the index of the element the new table follows, `None` is the top of the file
+    let after = if let Some(seg) = seg_match {
+        let matching = tables
+            .iter()
+            .filter(|t| seg.is_segment_match(&heading_text(t)))
+            .collect::<Vec<_>>();
+        let new_path = heading_segments(&new_heading);
+        match matching
+            .iter()
+            .rev()
+            .find(|t| heading_segments(heading_text(t)) <= new_path)
+        {
+            Some(t) => Some(pos_of(t)),
+            None => match matching.first() {
+                Some(t) => pos_of(t).checked_sub(1),
+                None => kids.iter().rposition(|el| el.kind() != TomlKind::EoF),
+            },
+        }
+    } else {
+        last_with_prefix(&segs)
+            .or_else(|| {
+                segs.split_last()
+                    .filter(|(_, parent)| !parent.is_empty())
+                    .and_then(|(_, parent)| last_with_prefix(parent))
+            })
+            .or_else(|| kids.iter().rposition(|el| el.kind() != TomlKind::EoF))
+    };
+
+    let eol = line_ending(root);
+    // blank lines between tables follow the first pair of tables in the file
+    let sep = tables
+        .iter()
+        .find(|t| t.next_sibling().map(|n| n.kind()) == Some(TomlKind::Table))
+        .map(|t| trailing_ws(&t.clone().into()).matches('\n').count())
+        .filter(|count| *count > 0)
+        .map(|count| eol.repeat(count))
+        .unwrap_or_else(|| eol.repeat(2));
+
+    let mut greens = kids.iter().map(green_element).collect::<Vec<_>>();
+    match after {
+        Some(a) => {
+            let ws = trailing_ws(&kids[a]);
+            let is_last = kids[a + 1..].iter().all(|el| el.kind() == TomlKind::EoF);
+            let (anchor_ws, new_ws) = if is_last { (sep, ws) } else { (ws, sep) };
+            greens[a] = with_trailing_ws(&kids[a], &anchor_ws);
+            let table = node(TomlKind::Table, vec![heading_node(&segs, array, &new_ws)]);
+            greens.insert(a + 1, table);
+        }
+        None => {
+            let new_ws = if kids.iter().any(|el| el.kind() != TomlKind::EoF) {
+                sep
+            } else {
+                eol.to_string()
+            };
+            let table = node(TomlKind::Table, vec![heading_node(&segs, array, &new_ws)]);
+            greens.insert(0, table);
+        }
+    }
+    Ok(replace_children(root, greens))
+}
+
+/// Creates an empty `[table]` at `path`.
+///
+/// The table is placed after the last table sharing its parent heading or at
+/// the end of the document. When a `Matcher` is given and one of its `segmented`
+/// headings matches, the table is placed at its sorted position among them.
+pub fn insert_table<S: AsRef<str>>(
+    root: &SyntaxNode,
+    path: &[S],
+    matcher: Option<&Matcher<'_>>,
+) -> TomlResult<SyntaxNode> {
+    insert_heading(root, path, false, matcher)
+}
+
+/// Creates a new, empty `[[array.of.tables]]` entry at `path`.
+///
+/// The entry is placed after the last entry of the same array and its
+/// sub-tables, otherwise it is placed like `insert_table` would.
+pub fn insert_array_table<S: AsRef<str>>(
+    root: &SyntaxNode,
+    path: &[S],
+    matcher: Option<&Matcher<'_>>,
+) -> TomlResult<SyntaxNode> {
+    insert_heading(root, path, true, matcher)
+}
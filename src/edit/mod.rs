@@ -0,0 +1,507 @@
+//! Format preserving edits of a parsed toml tree.
+//!
+//! Each edit rebuilds only the subtree that changed, the rest of the green
+//! tree is shared with the original so anything not touched by the edit
+//! is kept byte for byte.
+
+use rowan::{GreenNode, GreenToken, NodeOrToken, SmolStr};
+
+use super::common::err::{ParseTomlError, TomlErrorKind, TomlResult};
+use super::sort;
+use super::tkn_tree::{
+    parse_it, path::find_key_value, SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind,
+};
+
+mod convert;
+mod insert;
+mod remove;
+mod rename;
+mod text;
+
+pub use convert::{convert_table, TableForm};
+pub use insert::{insert_array_table, insert_key_value, insert_table};
+pub use remove::{remove_key_value, remove_table};
+pub use rename::rename;
+pub use text::{apply_edits, text_edits, TextEdit};
+
+pub(crate) use insert::{insert_raw, is_array_table};
+pub(crate) use text::common_pairs;
+
+pub(crate) type GreenElement = NodeOrToken<GreenNode, GreenToken>;
+
+pub(crate) fn green_element(el: &SyntaxElement) -> GreenElement {
+    match el {
+        SyntaxElement::Node(n) => NodeOrToken::Node(n.green().clone()),
+        SyntaxElement::Token(t) => NodeOrToken::Token(t.green().clone()),
+    }
+}
+
+pub(crate) fn token(kind: TomlKind, text: &str) -> GreenElement {
+    NodeOrToken::Token(GreenToken::new(kind.into(), SmolStr::new(text)))
+}
+
+pub(crate) fn node(kind: TomlKind, kids: Vec<GreenElement>) -> GreenElement {
+    NodeOrToken::Node(GreenNode::new(kind.into(), kids))
+}
+
+fn join_path<S: AsRef<str>>(path: &[S]) -> String {
+    path.iter()
+        .map(|s| s.as_ref())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+pub(crate) fn not_found<S: AsRef<str>>(path: &[S]) -> ParseTomlError {
+    ParseTomlError::new(
+        "no item found at key path".into(),
+        TomlErrorKind::KeyNotFound(join_path(path)),
+    )
+}
+
+pub(crate) fn key_exists<S: AsRef<str>>(path: &[S]) -> ParseTomlError {
+    ParseTomlError::new(
+        "an item already exists at key path".into(),
+        TomlErrorKind::KeyExists(join_path(path)),
+    )
+}
+
+/// Replaces the children of `parent` and returns the new root of the tree.
+pub(crate) fn replace_children(parent: &SyntaxNode, kids: Vec<GreenElement>) -> SyntaxNode {
+    let green = GreenNode::new(parent.kind().into(), kids);
+    SyntaxNode::new_root(parent.replace_with(green))
+}
+
+/// Rebuilds the tree below `node`, any node for which `f` returns a
+/// replacement is swapped out and not descended into. Returns `None` when
+/// nothing was replaced.
+pub(crate) fn rewrite_nodes<F>(node: &SyntaxNode, f: &mut F) -> Option<GreenNode>
+where
+    F: FnMut(&SyntaxNode) -> Option<GreenElement>,
+{
+    let mut changed = false;
+    let kids = node
+        .children_with_tokens()
+        .map(|el| match &el {
+            SyntaxElement::Node(n) => match f(n) {
+                Some(green) => {
+                    changed = true;
+                    green
+                }
+                None => match rewrite_nodes(n, f) {
+                    Some(green) => {
+                        changed = true;
+                        NodeOrToken::Node(green)
+                    }
+                    None => green_element(&el),
+                },
+            },
+            SyntaxElement::Token(_) => green_element(&el),
+        })
+        .collect::<Vec<_>>();
+    if changed {
+        Some(GreenNode::new(node.kind().into(), kids))
+    } else {
+        None
+    }
+}
+
+/// The text of the whitespace token `el` ends with.
+pub(crate) fn trailing_ws(el: &SyntaxElement) -> String {
+    let last = match el {
+        SyntaxElement::Node(n) => n.last_token(),
+        SyntaxElement::Token(t) => Some(t.clone()),
+    };
+    last.filter(|t| t.kind() == TomlKind::Whitespace)
+        .map(|t| t.text().to_string())
+        .unwrap_or_default()
+}
+
+/// Returns `el` with the whitespace at its very end replaced by `ws`, the
+/// whitespace stays in whichever node held it.
+pub(crate) fn with_trailing_ws(el: &SyntaxElement, ws: &str) -> GreenElement {
+    let parent = match el {
+        SyntaxElement::Token(t) if t.kind() == TomlKind::Whitespace => {
+            return token(TomlKind::Whitespace, ws)
+        }
+        SyntaxElement::Token(_) => return green_element(el),
+        SyntaxElement::Node(n) => n,
+    };
+    let mut kids = parent
+        .children_with_tokens()
+        .map(|el| green_element(&el))
+        .collect::<Vec<_>>();
+    match parent.last_child_or_token() {
+        Some(last) if last.kind() == TomlKind::Whitespace => {
+            kids.pop();
+            if !ws.is_empty() {
+                kids.push(token(TomlKind::Whitespace, ws));
+            }
+        }
+        Some(last) if !trailing_ws(&last).is_empty() => {
+            kids.pop();
+            kids.push(with_trailing_ws(&last, ws));
+        }
+        _ if !ws.is_empty() => kids.push(token(TomlKind::Whitespace, ws)),
+        _ => {}
+    }
+    node(parent.kind(), kids)
+}
+
+/// The text of the `Key` of a `KeyValue` node as written in the file.
+pub(crate) fn key_text(kv: &SyntaxNode) -> String {
+    kv.children()
+        .find(|n| n.kind() == TomlKind::Key)
+        .map(|k| k.token_text().trim().to_string())
+        .unwrap_or_default()
+}
+
+/// The index of the last element on the same line as the key value at `idx`,
+/// a comment following a key value on the same line belongs to it.
+pub(crate) fn line_end(kids: &[SyntaxElement], idx: usize) -> usize {
+    let same_line_comment = !trailing_ws(&kids[idx]).contains('\n')
+        && kids.get(idx + 1).map(|el| el.kind()) == Some(TomlKind::Comment);
+    if same_line_comment {
+        idx + 1
+    } else {
+        idx
+    }
+}
+
+/// The index of the first comment in the block of comments directly above
+/// `kids[end]`. A comment is attached when it sits on its own line and no
+/// blank line separates it from what follows.
+pub(crate) fn attached_comments(kids: &[SyntaxElement], end: usize) -> usize {
+    let mut start = end;
+    while start > 0 && kids[start - 1].kind() == TomlKind::Comment {
+        let own_line = start == 1 || trailing_ws(&kids[start - 2]).contains('\n');
+        if !own_line || trailing_ws(&kids[start - 1]).matches('\n').count() != 1 {
+            break;
+        }
+        start -= 1;
+    }
+    start
+}
+
+/// Builds a `Comment` node from the text of a comment, `#` included.
+pub(crate) fn comment_node(text: &str, trailing: &str) -> GreenElement {
+    let text = text.trim();
+    let mut kids = vec![
+        token(TomlKind::Hash, "#"),
+        token(
+            TomlKind::CommentText,
+            text.strip_prefix('#').unwrap_or(text),
+        ),
+    ];
+    if !trailing.is_empty() {
+        kids.push(token(TomlKind::Whitespace, trailing));
+    }
+    node(TomlKind::Comment, kids)
+}
+
+/// Returns `kv` without any whitespace before its `Key`.
+pub(crate) fn without_leading_ws(kv: &SyntaxNode) -> Vec<GreenElement> {
+    kv.children_with_tokens()
+        .skip_while(|el| el.kind() == TomlKind::Whitespace)
+        .map(|el| green_element(&el))
+        .collect()
+}
+
+/// The line ending used in the document, `\r\n` if any line uses it.
+pub(crate) fn line_ending(root: &SyntaxNode) -> &'static str {
+    if root.token_text().contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// The indentation of the line `node` starts on.
+pub(crate) fn line_indent(node: &SyntaxNode) -> String {
+    let start = node
+        .children()
+        .find(|n| n.kind() == TomlKind::Key)
+        .unwrap_or_else(|| node.clone())
+        .text_range()
+        .start();
+    let root = node.ancestors().last().unwrap().token_text();
+    let before = &root[..usize::from(start)];
+    let line = &before[before.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+    if line.chars().all(|c| c == ' ' || c == '\t') {
+        line.to_string()
+    } else {
+        String::default()
+    }
+}
+
+/// Quotes a key segment if it contains anything besides the characters
+/// allowed in a bare key.
+pub(crate) fn quote_key(seg: &str) -> String {
+    let bare = !seg.is_empty()
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        seg.to_string()
+    } else {
+        format!("\"{}\"", seg.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// Builds a `Key` node from the key as written, quotes and dots included. The
+/// key is a single `Ident` as the tokenizer produces it.
+pub(crate) fn key_node(raw_key: &str) -> GreenElement {
+    node(TomlKind::Key, vec![token(TomlKind::Ident, raw_key)])
+}
+
+/// Builds a `KeyValue` node, `eq` is the whitespace before and after the `=`.
+pub(crate) fn key_value_node(
+    raw_key: &str,
+    eq: (&str, &str),
+    value: &SyntaxNode,
+    trailing: &str,
+) -> GreenElement {
+    node(
+        TomlKind::KeyValue,
+        key_value_kids(raw_key, eq, value, trailing),
+    )
+}
+
+/// The children of a `KeyValue` node as built by `key_value_node`.
+pub(crate) fn key_value_kids(
+    raw_key: &str,
+    eq: (&str, &str),
+    value: &SyntaxNode,
+    trailing: &str,
+) -> Vec<GreenElement> {
+    let mut kids = vec![key_node(raw_key)];
+    if !eq.0.is_empty() {
+        kids.push(token(TomlKind::Whitespace, eq.0));
+    }
+    kids.push(token(TomlKind::Equal, "="));
+    if !eq.1.is_empty() {
+        kids.push(token(TomlKind::Whitespace, eq.1));
+    }
+    kids.push(NodeOrToken::Node(value.green().clone()));
+    if !trailing.is_empty() {
+        kids.push(token(TomlKind::Whitespace, trailing));
+    }
+    kids
+}
+
+/// The whitespace before and after the `=` of a `KeyValue` node.
+pub(crate) fn eq_spacing(kv: &SyntaxNode) -> (String, String) {
+    let kids = kv.children_with_tokens().collect::<Vec<_>>();
+    let ws = |el: Option<&SyntaxElement>| {
+        el.and_then(|el| el.as_token())
+            .filter(|t| t.kind() == TomlKind::Whitespace)
+            .map(|t| t.text().to_string())
+            .unwrap_or_default()
+    };
+    match kids.iter().position(|el| el.kind() == TomlKind::Equal) {
+        Some(idx) if idx > 0 => (ws(kids.get(idx - 1)), ws(kids.get(idx + 1))),
+        _ => (" ".into(), " ".into()),
+    }
+}
+
+/// Builds a `Heading` node, `[a.b]` or `[[a.b]]` when `array` is true.
+pub(crate) fn heading_node<S: AsRef<str>>(path: &[S], array: bool, trailing: &str) -> GreenElement {
+    let raw = path
+        .iter()
+        .map(|s| quote_key(s.as_ref()))
+        .collect::<Vec<_>>();
+    heading_node_raw(&raw, array, trailing)
+}
+
+/// Builds a `Heading` node from segments as they are written, quoted
+/// segments become `Str` nodes.
+pub(crate) fn heading_node_raw<S: AsRef<str>>(
+    raw: &[S],
+    array: bool,
+    trailing: &str,
+) -> GreenElement {
+    let seg = |s: &str| match s.chars().next() {
+        Some(q) if (q == '"' || q == '\'') && s.len() > 1 => {
+            let quote = if q == '"' {
+                TomlKind::DoubleQuote
+            } else {
+                TomlKind::SingleQuote
+            };
+            let q = q.to_string();
+            node(
+                TomlKind::Str,
+                vec![
+                    token(quote, &q),
+                    token(TomlKind::Ident, &s[1..s.len() - 1]),
+                    token(quote, &q),
+                ],
+            )
+        }
+        _ => token(TomlKind::Ident, s),
+    };
+    let ident = match raw {
+        [single] if !single.as_ref().starts_with(['"', '\'']) => seg(single.as_ref()),
+        _ => {
+            let mut kids = Vec::default();
+            for (i, s) in raw.iter().enumerate() {
+                if i > 0 {
+                    kids.push(token(TomlKind::Dot, "."));
+                }
+                kids.push(seg(s.as_ref()));
+            }
+            node(TomlKind::SegIdent, kids)
+        }
+    };
+
+    let mut kids = vec![token(TomlKind::OpenBrace, "[")];
+    if array {
+        kids.push(token(TomlKind::OpenBrace, "["));
+    }
+    kids.push(ident);
+    kids.push(token(TomlKind::CloseBrace, "]"));
+    if array {
+        kids.push(token(TomlKind::CloseBrace, "]"));
+    }
+    if !trailing.is_empty() {
+        kids.push(token(TomlKind::Whitespace, trailing));
+    }
+    if array {
+        node(TomlKind::Heading, vec![node(TomlKind::ArrayHeading, kids)])
+    } else {
+        node(TomlKind::Heading, kids)
+    }
+}
+
+/// Rebuilds an `InlineTable` node with `entries` as its key value pairs. The
+/// padding inside the braces, the whitespace after each comma and any trailing
+/// comma are taken from the original.
+///
+/// Each entry is the children of a `KeyValue` node without leading whitespace.
+pub(crate) fn rebuild_inline_table(
+    table: &SyntaxNode,
+    entries: Vec<Vec<GreenElement>>,
+) -> GreenElement {
+    let kids = table.children_with_tokens().collect::<Vec<_>>();
+    let kvs = table
+        .children()
+        .filter(|n| n.kind() == TomlKind::KeyValue)
+        .collect::<Vec<_>>();
+    let ws_text = |el: Option<&SyntaxElement>| {
+        el.and_then(|el| el.as_token())
+            .filter(|t| t.kind() == TomlKind::Whitespace)
+            .map(|t| t.text().to_string())
+    };
+    let curly = kids
+        .iter()
+        .position(|el| el.kind() == TomlKind::OpenCurly)
+        .unwrap_or(0);
+    let close = kids
+        .iter()
+        .rposition(|el| el.kind() == TomlKind::CloseCurly)
+        .unwrap_or(kids.len());
+
+    let open_pad = match kvs.first() {
+        Some(kv) => ws_text(kv.first_child_or_token().as_ref()).unwrap_or_default(),
+        None => ws_text(kids.get(curly + 1)).unwrap_or_else(|| " ".into()),
+    };
+    let close_pad = match kvs.last() {
+        Some(_) => ws_text(kids.get(close.wrapping_sub(1))).unwrap_or_default(),
+        None => open_pad.clone(),
+    };
+    let sep = kids
+        .iter()
+        .position(|el| el.kind() == TomlKind::Comma)
+        .and_then(|idx| ws_text(kids.get(idx + 1)))
+        .unwrap_or_else(|| " ".into());
+    let trailing_comma = kids[..close]
+        .iter()
+        .rev()
+        .find(|el| el.kind() != TomlKind::Whitespace)
+        .map(|el| el.kind() == TomlKind::Comma)
+        == Some(true);
+
+    let mut new_kids = kids[..=curly].iter().map(green_element).collect::<Vec<_>>();
+    let count = entries.len();
+    for (i, mut entry) in entries.into_iter().enumerate() {
+        if i == 0 && !open_pad.is_empty() {
+            entry.insert(0, token(TomlKind::Whitespace, &open_pad));
+        }
+        new_kids.push(node(TomlKind::KeyValue, entry));
+        if i + 1 < count || trailing_comma {
+            new_kids.push(token(TomlKind::Comma, ","));
+        }
+        if i + 1 < count {
+            new_kids.push(token(TomlKind::Whitespace, &sep));
+        }
+    }
+    if count > 0 && !close_pad.is_empty() {
+        new_kids.push(token(TomlKind::Whitespace, &close_pad));
+    }
+    new_kids.extend(kids[close..].iter().map(green_element));
+    node(TomlKind::InlineTable, new_kids)
+}
+
+/// Parses `value` as the right hand side of a key value pair using the
+/// tokenizer and returns the resulting `Value` node.
+pub(crate) fn parse_value(value: &str) -> TomlResult<SyntaxNode> {
+    let text = value.trim();
+    let invalid = || {
+        ParseTomlError::new(
+            "invalid value".into(),
+            TomlErrorKind::InternalParseError(text.into()),
+        )
+    };
+    if text.is_empty() {
+        return Err(invalid());
+    }
+
+    let root = parse_it(&format!("key = {}", text))?.syntax();
+    let mut kids = root.children();
+    match (kids.next(), kids.next()) {
+        (Some(kv), None) if kv.kind() == TomlKind::KeyValue => kv
+            .children()
+            .find(|n| n.kind() == TomlKind::Value)
+            .filter(|val| val.token_text() == text)
+            .ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+/// Replaces the value found at `path` with `value`, keeping the key, the
+/// surrounding whitespace, comments and the rest of the document untouched.
+///
+/// `value` is any valid toml value as it would be written in the file,
+/// strings must be quoted. For arrays of tables the first entry holding the
+/// key is used.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, replace_value, SyntaxNodeExtTrait};
+///
+/// let toml = "[package]\nversion = \"0.1.0\" # bumped by ci\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let bumped = replace_value(&root, &["package", "version"], "\"0.2.0\"").unwrap();
+/// assert_eq!(bumped.token_text(), "[package]\nversion = \"0.2.0\" # bumped by ci\n");
+/// ```
+pub fn replace_value<S: AsRef<str>>(
+    root: &SyntaxNode,
+    path: &[S],
+    value: &str,
+) -> TomlResult<SyntaxNode> {
+    let old = find_key_value(root, path)
+        .and_then(|kv| kv.children().find(|n| n.kind() == TomlKind::Value))
+        .ok_or_else(|| not_found(path))?;
+    let new = parse_value(value)?;
+
+    let is_ws = |el: &SyntaxElement| el.kind() == TomlKind::Whitespace;
+    // inline table values own their leading whitespace, keep it
+    let children = old
+        .children_with_tokens()
+        .take_while(is_ws)
+        .chain(new.children_with_tokens().skip_while(is_ws))
+        .map(|el| green_element(&el))
+        .collect::<Vec<_>>();
+
+    let green = GreenNode::new(TomlKind::Value.into(), children);
+    Ok(SyntaxNode::new_root(old.replace_with(green)))
+}
@@ -0,0 +1,185 @@
+use super::{
+    attached_comments, green_element, line_end, node, not_found, rebuild_inline_table,
+    replace_children, trailing_ws, with_trailing_ws, without_leading_ws, GreenElement,
+};
+use crate::common::err::TomlResult;
+use crate::tkn_tree::{
+    path::{find_key_value, heading_path},
+    SyntaxElement, SyntaxNode, TomlKind,
+};
+
+/// The whitespace left after the element before a removed item. When nothing
+/// follows, the removed item's whitespace ends the document or table, otherwise
+/// the larger gap of the two is kept so blank lines between groups survive.
+fn joined_ws(before: &str, removed: &str, is_last: bool) -> String {
+    if is_last || removed.matches('\n').count() > before.matches('\n').count() {
+        removed.to_string()
+    } else {
+        before.to_string()
+    }
+}
+
+/// Removes `kids[start..=end]` and fixes up the whitespace of the element
+/// before the removed range.
+fn remove_range(kids: &[SyntaxElement], start: usize, end: usize) -> Vec<GreenElement> {
+    let is_last = kids[end + 1..].iter().all(|el| el.kind() == TomlKind::EoF);
+    let mut greens = kids[..start].iter().map(green_element).collect::<Vec<_>>();
+    if let Some(prev) = start.checked_sub(1) {
+        let ws = joined_ws(&trailing_ws(&kids[prev]), &trailing_ws(&kids[end]), is_last);
+        greens[prev] = with_trailing_ws(&kids[prev], &ws);
+    }
+    greens.extend(kids[end + 1..].iter().map(green_element));
+    greens
+}
+
+fn remove_table_node(root: &SyntaxNode, table: &SyntaxNode) -> SyntaxNode {
+    let kids = root.children_with_tokens().collect::<Vec<_>>();
+    let idx = kids
+        .iter()
+        .position(|el| el.as_node() == Some(table))
+        .unwrap();
+    let table_kids = table.children_with_tokens().collect::<Vec<_>>();
+
+    // comments at the end of a table are attached to the next heading, they stay
+    let has_next = kids[idx + 1..]
+        .iter()
+        .any(|el| el.kind() == TomlKind::Table);
+    let keep = if has_next {
+        attached_comments(&table_kids, table_kids.len()).max(1)
+    } else {
+        table_kids.len()
+    };
+    let kept = table_kids[keep..].iter().map(green_element);
+    let removed_ws = trailing_ws(&table_kids[keep - 1]);
+    let is_last =
+        keep == table_kids.len() && kids[idx + 1..].iter().all(|el| el.kind() == TomlKind::EoF);
+
+    // the comments attached to this heading belong to the previous table
+    match kids[..idx].last().and_then(|el| el.as_node()) {
+        Some(prev) if prev.kind() == TomlKind::Table => {
+            let prev_kids = prev.children_with_tokens().collect::<Vec<_>>();
+            let start = attached_comments(&prev_kids, prev_kids.len()).max(1);
+            let ws = joined_ws(&trailing_ws(&prev_kids[start - 1]), &removed_ws, is_last);
+
+            let mut greens = prev_kids[..start - 1]
+                .iter()
+                .map(green_element)
+                .collect::<Vec<_>>();
+            greens.push(with_trailing_ws(&prev_kids[start - 1], &ws));
+            greens.extend(kept);
+
+            let mut root_greens = kids.iter().map(green_element).collect::<Vec<_>>();
+            root_greens[idx - 1] = node(TomlKind::Table, greens);
+            root_greens.remove(idx);
+            replace_children(root, root_greens)
+        }
+        _ => {
+            let start = attached_comments(&kids, idx);
+            let mut greens = kids[..start].iter().map(green_element).collect::<Vec<_>>();
+            if let Some(prev) = start.checked_sub(1) {
+                let ws = joined_ws(&trailing_ws(&kids[prev]), &removed_ws, is_last);
+                greens[prev] = with_trailing_ws(&kids[prev], &ws);
+            }
+            greens.extend(kept);
+            greens.extend(kids[idx + 1..].iter().map(green_element));
+            replace_children(root, greens)
+        }
+    }
+}
+
+/// Removes the `[table]` at `path` along with the comments directly above its
+/// heading. For arrays of tables the first entry is removed. Sub-tables such
+/// as `[path.more]` are separate tables and are left in place.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, remove_table, SyntaxNodeExtTrait};
+///
+/// let toml = "[package]\nname = \"a\"\n\n# only for tests\n[dev-dependencies]\nb = \"1\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let removed = remove_table(&root, &["dev-dependencies"]).unwrap();
+/// assert_eq!(removed.token_text(), "[package]\nname = \"a\"\n");
+/// ```
+pub fn remove_table<S: AsRef<str>>(root: &SyntaxNode, path: &[S]) -> TomlResult<SyntaxNode> {
+    let table = root
+        .children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .find(|t| {
+            let found = heading_path(t);
+            found.len() == path.len() && found.iter().zip(path).all(|(a, b)| a == b.as_ref())
+        })
+        .ok_or_else(|| not_found(path))?;
+    Ok(remove_table_node(root, &table))
+}
+
+/// Removes the key value pair at `path` along with the comments directly
+/// above it, a comment on the same line and its line ending.
+///
+/// When `remove_empty_table` is true and the key was the last item of its
+/// table, the now empty heading is removed as well.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, remove_key_value, SyntaxNodeExtTrait};
+///
+/// let toml = "[package]\nname = \"a\"\n# pinned\nversion = \"0.1.0\" # bumped by ci\nedition = \"2018\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let removed = remove_key_value(&root, &["package", "version"], false).unwrap();
+/// assert_eq!(removed.token_text(), "[package]\nname = \"a\"\nedition = \"2018\"\n");
+/// ```
+pub fn remove_key_value<S: AsRef<str>>(
+    root: &SyntaxNode,
+    path: &[S],
+    remove_empty_table: bool,
+) -> TomlResult<SyntaxNode> {
+    let kv = find_key_value(root, path).ok_or_else(|| not_found(path))?;
+    let parent = kv.parent().ok_or_else(|| not_found(path))?;
+
+    if parent.kind() == TomlKind::InlineTable {
+        let entries = parent
+            .children()
+            .filter(|n| n.kind() == TomlKind::KeyValue && *n != kv)
+            .map(|n| without_leading_ws(&n))
+            .collect();
+        let green = rebuild_inline_table(&parent, entries);
+        return Ok(SyntaxNode::new_root(
+            parent.replace_with(green.into_node().unwrap()),
+        ));
+    }
+
+    let kids = parent.children_with_tokens().collect::<Vec<_>>();
+    let idx = kids
+        .iter()
+        .position(|el| el.as_node() == Some(&kv))
+        .unwrap();
+    let start = attached_comments(&kids, idx);
+    let end = line_end(&kids, idx);
+    let new_root = replace_children(&parent, remove_range(&kids, start, end));
+
+    if !remove_empty_table || parent.kind() != TomlKind::Table {
+        return Ok(new_root);
+    }
+    // the same table in the new tree, top level tables are not reordered by the edit
+    let nth = parent
+        .parent()
+        .map(|root| {
+            root.children()
+                .take_while(|n| *n != parent)
+                .filter(|n| n.kind() == TomlKind::Table)
+                .count()
+        })
+        .unwrap_or_default();
+    let table = new_root
+        .children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .nth(nth)
+        .unwrap();
+    let is_empty = table.children().all(|n| n.kind() == TomlKind::Heading);
+    if is_empty {
+        Ok(remove_table_node(&new_root, &table))
+    } else {
+        Ok(new_root)
+    }
+}
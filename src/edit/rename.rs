@@ -0,0 +1,151 @@
+use super::{
+    green_element, heading_node_raw, key_exists, node, not_found, quote_key, rewrite_nodes, token,
+    trailing_ws, GreenElement,
+};
+use crate::common::err::TomlResult;
+use crate::tkn_tree::{
+    path::{heading_path, key_path, split_key, split_raw},
+    SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind,
+};
+
+/// Replaces the segment at `idx` of a dotted key or heading keeping the other
+/// segments and the whitespace around the replaced one as written.
+fn rename_segment(raw: &[String], idx: usize, name: &str) -> Vec<String> {
+    let mut raw = raw.to_vec();
+    let seg = &raw[idx];
+    let lead = &seg[..seg.len() - seg.trim_start().len()];
+    let trail = &seg[seg.trim_end().len()..];
+    raw[idx] = format!("{}{}{}", lead, quote_key(name), trail);
+    raw
+}
+
+fn starts_with<S: AsRef<str>>(found: &[String], path: &[S]) -> bool {
+    found.len() >= path.len() && found.iter().zip(path).all(|(a, b)| a == b.as_ref())
+}
+
+fn rename_heading(table: &SyntaxNode, idx: usize, name: &str) -> Option<GreenElement> {
+    let heading = table.children().find(|n| n.kind() == TomlKind::Heading)?;
+    let array = heading.first_child().map(|n| n.kind()) == Some(TomlKind::ArrayHeading);
+    let text = heading.token_text();
+    let raw = split_raw(text.trim().trim_start_matches('[').trim_end_matches(']'))
+        .iter()
+        .map(|s| s.trim().to_string())
+        .collect::<Vec<_>>();
+    let renamed = rename_segment(&raw, idx, name);
+    let ws = trailing_ws(&SyntaxElement::Node(heading));
+
+    let mut kids = table
+        .children_with_tokens()
+        .map(|el| green_element(&el))
+        .collect::<Vec<_>>();
+    kids[0] = heading_node_raw(&renamed, array, &ws);
+    Some(node(TomlKind::Table, kids))
+}
+
+fn rename_key(kv: &SyntaxNode, idx: usize, name: &str) -> Option<GreenElement> {
+    let key = kv.children().find(|n| n.kind() == TomlKind::Key)?;
+    let text = key.token_text();
+    let raw = split_raw(&text);
+    let renamed = rename_segment(&raw, idx, name).join(".");
+
+    let kids = kv
+        .children_with_tokens()
+        .map(|el| match el.as_node() {
+            Some(n) if *n == key => node(TomlKind::Key, vec![token(TomlKind::Ident, &renamed)]),
+            _ => green_element(&el),
+        })
+        .collect();
+    Some(node(TomlKind::KeyValue, kids))
+}
+
+/// Renames the segment of a `KeyValue`'s own key that lines up with `idx`
+/// of the full key path.
+fn rename_in_key_value<S: AsRef<str>>(
+    kv: &SyntaxNode,
+    path: &[S],
+    idx: usize,
+    name: &str,
+) -> Option<GreenElement> {
+    let full = key_path(kv);
+    let own = kv
+        .children()
+        .find(|n| n.kind() == TomlKind::Key)
+        .map(|k| split_key(&k.token_text()).len())?;
+    let prefix = full.len() - own;
+    if starts_with(&full, path) && prefix <= idx {
+        rename_key(kv, idx - prefix, name)
+    } else {
+        None
+    }
+}
+
+/// Renames the key or table at `path` to `name` everywhere it appears, in
+/// table headings, segments of dotted keys and keys of inline tables. The new
+/// name is quoted when it is not a valid bare key.
+///
+/// Returns an error if nothing exists at `path` or if anything already
+/// exists under the new name.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, rename, SyntaxNodeExtTrait};
+///
+/// let toml = "[dev-dependencies]\na = \"1\"\n\n[dev-dependencies.b]\nversion = \"2\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let renamed = rename(&root, &["dev-dependencies"], "dev_dependencies").unwrap();
+/// assert_eq!(
+///     renamed.token_text(),
+///     "[dev_dependencies]\na = \"1\"\n\n[dev_dependencies.b]\nversion = \"2\"\n"
+/// );
+/// ```
+pub fn rename<S: AsRef<str>>(root: &SyntaxNode, path: &[S], name: &str) -> TomlResult<SyntaxNode> {
+    let idx = match path.len().checked_sub(1) {
+        Some(idx) => idx,
+        None => return Err(not_found(path)),
+    };
+    let mut target = path
+        .iter()
+        .map(|s| s.as_ref().to_string())
+        .collect::<Vec<_>>();
+    target[idx] = name.to_string();
+
+    let kv_paths = root
+        .descendants()
+        .filter(|n| n.kind() == TomlKind::KeyValue)
+        .map(|kv| key_path(&kv))
+        .collect::<Vec<_>>();
+    let table_paths = root
+        .children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .map(|t| heading_path(&t))
+        .collect::<Vec<_>>();
+    let exists = |p: &[String]| {
+        kv_paths
+            .iter()
+            .chain(table_paths.iter())
+            .any(|found| starts_with(found, p))
+    };
+    if exists(&target) && target[idx] != path[idx].as_ref() {
+        return Err(key_exists(&target));
+    }
+
+    let mut found = false;
+    let new = rewrite_nodes(root, &mut |n| match n.kind() {
+        // the keys of a renamed table are all below the renamed segment
+        TomlKind::Table if starts_with(&heading_path(n), path) => {
+            found = true;
+            rename_heading(n, idx, name)
+        }
+        TomlKind::KeyValue => {
+            let green = rename_in_key_value(n, path, idx, name);
+            found |= green.is_some();
+            green
+        }
+        _ => None,
+    });
+    match new {
+        Some(green) if found => Ok(SyntaxNode::new_root(green)),
+        _ => Err(not_found(path)),
+    }
+}
@@ -0,0 +1,193 @@
+use rowan::{TextRange, TextSize};
+
+use crate::tkn_tree::{walk::walk_tokens, SyntaxNode, SyntaxToken};
+
+/// A change to the text of a document, `range` of the original text is
+/// replaced by `replacement`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: TextRange,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// Creates the smallest edit that turns `old`, found at `start` of the
+    /// original text, into `new`. Returns `None` when they are the same.
+    pub(crate) fn minimal(old: &str, start: usize, new: &str) -> Option<TextEdit> {
+        let prefix = old
+            .char_indices()
+            .zip(new.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| old.len().min(new.len()));
+        let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+        let suffix = old_rest
+            .char_indices()
+            .rev()
+            .zip(new_rest.chars().rev())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, a), _)| old_rest.len() - i - a.len_utf8())
+            .unwrap_or_else(|| old_rest.len().min(new_rest.len()));
+        if old_rest.len() == suffix && new_rest.len() == suffix {
+            return None;
+        }
+
+        let from = start + prefix;
+        let to = start + old.len() - suffix;
+        Some(TextEdit {
+            range: TextRange::new(TextSize::from(from as u32), TextSize::from(to as u32)),
+            replacement: new_rest[..new_rest.len() - suffix].to_string(),
+        })
+    }
+}
+
+/// Applies `edits` to `text`, the edits must not overlap.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{apply_edits, parse_it, replace_value, text_edits, SyntaxNodeExtTrait};
+///
+/// let toml = "[package]\nversion = \"0.1.0\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+/// let bumped = replace_value(&root, &["package", "version"], "\"0.2.0\"").unwrap();
+///
+/// let edits = text_edits(&root, &bumped);
+/// assert_eq!(edits.len(), 1);
+/// assert_eq!(edits[0].replacement, "2");
+/// assert_eq!(apply_edits(toml, &edits), bumped.token_text());
+/// ```
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|e| e.range.start());
+
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for edit in edits {
+        out.push_str(&text[last..usize::from(edit.range.start())]);
+        out.push_str(&edit.replacement);
+        last = usize::from(edit.range.end());
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// The most insertions and deletions `common_pairs` searches for, past this
+/// the sequences are treated as having nothing in common.
+const MAX_EDITS: isize = 1024;
+
+/// The pairs of indices of the longest common subsequence of `a` and `b`
+/// using Myers' diff algorithm.
+///
+/// When more than `MAX_EDITS` edits are needed no pairs are returned, so what
+/// lies between is replaced as a whole. This bounds the memory the search
+/// keeps for tracing the path back.
+pub(crate) fn common_pairs<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m + 1;
+    let idx = |k: isize| (k + offset) as usize;
+    let mut v = vec![0_isize; (2 * offset + 1) as usize];
+    // the diagonals `-d..=d` of `v` before each round `d`
+    let mut trace = Vec::default();
+
+    'search: for d in 0..=(n + m) {
+        if d > MAX_EDITS {
+            return Vec::default();
+        }
+        trace.push(v[idx(-d)..=idx(d)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) {
+                v[idx(k + 1)]
+            } else {
+                v[idx(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut pairs = Vec::default();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = if d > 0 { at(prev_k) } else { 0 };
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        if d > 0 {
+            x = prev_x;
+            y = prev_y;
+        }
+    }
+    pairs.reverse();
+    pairs
+}
+
+/// Computes the text edits that turn the text of `old` into the text of `new`.
+///
+/// Both trees are compared token by token so the edits follow the structure of
+/// the document, each edit is then shrunk to the characters that changed. The
+/// ranges refer to the text of `old` and the edits are sorted by position.
+pub fn text_edits(old: &SyntaxNode, new: &SyntaxNode) -> Vec<TextEdit> {
+    let a = walk_tokens(old).collect::<Vec<_>>();
+    let b = walk_tokens(new).collect::<Vec<_>>();
+    let same = |t: &SyntaxToken| (t.kind(), t.text().clone());
+    let a_keys = a.iter().map(same).collect::<Vec<_>>();
+    let b_keys = b.iter().map(same).collect::<Vec<_>>();
+
+    // most edits touch a small part of the document, only diff what changed
+    let head = a_keys
+        .iter()
+        .zip(&b_keys)
+        .take_while(|(x, y)| x == y)
+        .count();
+    let tail = a_keys[head..]
+        .iter()
+        .rev()
+        .zip(b_keys[head..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let pairs = common_pairs(&a_keys[head..a.len() - tail], &b_keys[head..b.len() - tail]);
+
+    let text = old.text().to_string();
+    let offset_of = |i: usize| {
+        a.get(i)
+            .map(|t| usize::from(t.text_range().start()))
+            .unwrap_or_else(|| text.len())
+    };
+    let mut edits = Vec::default();
+    let (mut i, mut j) = (head, head);
+    let ends = pairs
+        .into_iter()
+        .map(|(x, y)| (x + head, y + head))
+        .chain(Some((a.len() - tail, b.len() - tail)));
+    for (x, y) in ends {
+        if x > i || y > j {
+            let (start, end) = (offset_of(i), offset_of(x));
+            let replacement = b[j..y]
+                .iter()
+                .map(|t| t.text().as_str())
+                .collect::<String>();
+            edits.extend(TextEdit::minimal(&text[start..end], start, &replacement));
+        }
+        i = x + 1;
+        j = y + 1;
+    }
+    edits
+}
@@ -1,15 +1,30 @@
 mod common;
+mod diff;
+mod edit;
 mod sort;
 // mod struc;
 mod tkn_tree;
 mod toml_fmt;
 
-pub use sort::{sort_toml_items, Matcher};
+pub use common::err::{ParseTomlError, TomlErrorKind, TomlResult};
+pub use diff::{
+    content_hash, diff, merge, overlay, semantic_eq, ArrayStrategy, Conflict, DiffKind, Difference,
+    KeySegment, Layer, Merged, Overlaid,
+};
+pub use edit::{
+    apply_edits, convert_table, insert_array_table, insert_key_value, insert_table,
+    remove_key_value, remove_table, rename, replace_value, text_edits, TableForm, TextEdit,
+};
+pub use sort::{
+    check_sorted, order_tables, sort_array_tables, sort_in_place, sort_toml_items, ArrayOrder,
+    KeyOrder, Matcher, SortConfig, SortConfigBuilder, Sorted, Violation, ViolationKind,
+    CARGO_TABLE_ORDER,
+};
 // pub use struc::{Heading, InTable, KvPair, Table, Toml, Value};
 pub use tkn_tree::{
     parse_it,
     walk::{walk, walk_tokens, walk_tokens_non_ws},
     ParsedToml, Parser, SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, SyntaxToken, Tokenizer,
-    TomlKind,
+    TomlDate, TomlKind, ValueExtTrait,
 };
-pub use toml_fmt::Formatter;
+pub use toml_fmt::{FormatOptions, Formatter, IndentStyle, NewlineStyle};
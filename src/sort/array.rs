@@ -0,0 +1,272 @@
+use std::cmp::Ordering;
+
+use rowan::GreenNode;
+
+use crate::diff::doc::{value_item, Item, Value};
+use crate::edit::{green_element, node, token, GreenElement};
+use crate::tkn_tree::{SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlDate, TomlKind};
+
+/// How the items of a sorted array are ordered.
+///
+/// Items are compared by their decoded value, `'a'` and `"a"` are equal and
+/// `9` comes before `10`. Booleans come first, then numbers, strings, dates
+/// and last arrays and inline tables which keep their order. Items that
+/// compare equal keep their order.
+///
+/// A `Matcher` always uses the default order, the order of the arrays in the
+/// tables matching a heading is set with `SortConfigBuilder::array_order`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArrayOrder {
+    /// Compare strings ignoring case.
+    pub case_insensitive: bool,
+    /// Remove items that are the same as one before them, strings that only
+    /// differ by case are the same when `case_insensitive` is set.
+    pub dedup: bool,
+}
+
+/// The decoded value of an array item, values that fail to decode are
+/// compared by their text.
+pub(crate) struct ItemValue {
+    item: Option<Item>,
+    text: String,
+}
+
+impl ItemValue {
+    pub(crate) fn new(value: &SyntaxNode) -> Self {
+        Self {
+            item: value_item(value).ok(),
+            text: value.token_text().trim().to_string(),
+        }
+    }
+}
+
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Bool(_) => 0,
+        Value::Integer(_) | Value::Float(_) => 1,
+        Value::String(_) => 2,
+        Value::Date(_) => 3,
+        Value::Array(_) | Value::Table(_) => 4,
+    }
+}
+
+fn date_cmp(a: &TomlDate, b: &TomlDate) -> Ordering {
+    let rank = |d: &TomlDate| match d {
+        TomlDate::OffsetDateTime(_) => 0,
+        TomlDate::LocalDateTime(_) => 1,
+        TomlDate::LocalDate(_) => 2,
+        TomlDate::LocalTime(_) => 3,
+    };
+    match (a, b) {
+        // offset date times compare by the instant
+        (TomlDate::OffsetDateTime(a), TomlDate::OffsetDateTime(b)) => a.cmp(b),
+        (TomlDate::LocalDateTime(a), TomlDate::LocalDateTime(b)) => a.cmp(b),
+        (TomlDate::LocalDate(a), TomlDate::LocalDate(b)) => a.cmp(b),
+        (TomlDate::LocalTime(a), TomlDate::LocalTime(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+impl ArrayOrder {
+    pub(crate) fn compare(&self, a: &ItemValue, b: &ItemValue) -> Ordering {
+        let (a, b) = match (&a.item, &b.item) {
+            (Some(a), Some(b)) => (&a.value, &b.value),
+            _ => return a.text.cmp(&b.text),
+        };
+        rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Float(b)) => {
+                (*a as f64).partial_cmp(b).unwrap_or(Ordering::Equal)
+            }
+            (Value::Float(a), Value::Integer(b)) => {
+                a.partial_cmp(&(*b as f64)).unwrap_or(Ordering::Equal)
+            }
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::String(a), Value::String(b)) if self.case_insensitive => {
+                a.to_lowercase().cmp(&b.to_lowercase())
+            }
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Date(a), Value::Date(b)) => date_cmp(a, b),
+            _ => Ordering::Equal,
+        })
+    }
+
+    pub(crate) fn is_duplicate(&self, a: &ItemValue, b: &ItemValue) -> bool {
+        match (&a.item, &b.item) {
+            (Some(x), Some(y)) => match (&x.value, &y.value) {
+                (Value::String(x), Value::String(y)) if self.case_insensitive => {
+                    x.to_lowercase() == y.to_lowercase()
+                }
+                _ => x.same(y),
+            },
+            _ => a.text == b.text,
+        }
+    }
+}
+
+/// An `ArrayItem` split into what moves with the value and what stays with
+/// its place in the array.
+struct Slot {
+    /// The comments on the lines above the item, they move with the value.
+    lead: Vec<SyntaxElement>,
+    value: SyntaxElement,
+    decoded: ItemValue,
+    /// The comment after the value on the same line, it moves with the value.
+    comment: Option<SyntaxNode>,
+    before: Option<SyntaxElement>,
+    comma: Option<SyntaxElement>,
+    /// The whitespace after the value and comma.
+    gap: Option<SyntaxElement>,
+}
+
+impl Slot {
+    fn new(item: &SyntaxNode, lead: Vec<SyntaxElement>) -> Option<Self> {
+        let value = item.children().find(|n| n.kind() == TomlKind::Value)?;
+        let mut slot = Slot {
+            lead,
+            decoded: ItemValue::new(&value),
+            value: value.into(),
+            comment: None,
+            before: None,
+            comma: None,
+            gap: None,
+        };
+        let mut seen_value = false;
+        for el in item.children_with_tokens() {
+            match el.kind() {
+                TomlKind::Value => seen_value = true,
+                TomlKind::Whitespace if seen_value => slot.gap = Some(el),
+                TomlKind::Whitespace => slot.before = Some(el),
+                TomlKind::Comma => slot.comma = Some(el),
+                TomlKind::Comment => slot.comment = el.as_node().cloned(),
+                _ => {}
+            }
+        }
+        Some(slot)
+    }
+
+    /// The whitespace that ends the item, the line break and indent before the
+    /// next item of a multi-line array.
+    fn after(&self) -> Option<SyntaxElement> {
+        match &self.comment {
+            Some(comment) => comment
+                .last_child_or_token()
+                .filter(|el| el.kind() == TomlKind::Whitespace),
+            None => self.gap.clone(),
+        }
+    }
+}
+
+/// Builds the `ArrayItem` in the place of `slot` with the value and comment
+/// of `moved`.
+fn item(slot: &Slot, moved: &Slot) -> GreenElement {
+    let mut kids = slot
+        .before
+        .iter()
+        .chain(Some(&moved.value))
+        .chain(&slot.comma)
+        .map(green_element)
+        .collect::<Vec<_>>();
+
+    let after = slot.after();
+    match &moved.comment {
+        Some(comment) => {
+            match (&slot.comment, &slot.gap) {
+                (Some(_), Some(gap)) => kids.push(green_element(gap)),
+                _ => kids.push(token(TomlKind::Whitespace, " ")),
+            }
+            let mut comment = comment
+                .children_with_tokens()
+                .filter(|el| el.kind() != TomlKind::Whitespace)
+                .map(|el| green_element(&el))
+                .collect::<Vec<_>>();
+            // a comment runs to the end of the line
+            match after {
+                Some(SyntaxElement::Token(ws)) if ws.text().contains('\n') => {
+                    comment.push(green_element(&ws.into()))
+                }
+                _ => comment.push(token(TomlKind::Whitespace, "\n")),
+            }
+            kids.push(node(TomlKind::Comment, comment));
+        }
+        None => kids.extend(after.as_ref().map(green_element)),
+    }
+    node(TomlKind::ArrayItem, kids)
+}
+
+/// Sorts the items of an `Array` by `order`.
+///
+/// Each value moves along with the comment after it on the same line and the
+/// comments on the lines above it. The commas and whitespace stay in place so
+/// the indentation of a multi-line array and a trailing comma are kept.
+pub(crate) fn sort_array(array: &SyntaxNode, order: &ArrayOrder) -> GreenNode {
+    let mut head = Vec::default();
+    let mut slots = Vec::<Slot>::default();
+    let mut pending = Vec::default();
+    let mut tail = Vec::default();
+    for el in array.children_with_tokens() {
+        match el.kind() {
+            TomlKind::ArrayItem => {
+                let item = el.as_node().unwrap();
+                match Slot::new(item, std::mem::take(&mut pending)) {
+                    Some(slot) => slots.push(slot),
+                    // nothing to sort by, the array is left as it is
+                    None => return array.green().clone(),
+                }
+            }
+            TomlKind::Comment => {
+                // a comment on the line of the open brace stays with it
+                let same_line = match el.prev_sibling_or_token() {
+                    Some(SyntaxElement::Token(t)) if t.kind() == TomlKind::Whitespace => {
+                        !t.text().contains('\n')
+                    }
+                    _ => true,
+                };
+                if slots.is_empty() && pending.is_empty() && same_line {
+                    head.push(el);
+                } else {
+                    pending.push(el);
+                }
+            }
+            TomlKind::CloseBrace => {
+                tail.append(&mut pending);
+                tail.push(el);
+            }
+            _ if slots.is_empty() && pending.is_empty() => head.push(el),
+            _ => pending.push(el),
+        }
+    }
+
+    let mut order_of = (0..slots.len()).collect::<Vec<_>>();
+    if order.dedup {
+        let mut kept = Vec::<usize>::default();
+        for i in order_of {
+            match kept
+                .iter()
+                .find(|k| order.is_duplicate(&slots[**k].decoded, &slots[i].decoded))
+            {
+                // the comments above a removed item are kept above the first
+                Some(k) => {
+                    let mut lead = std::mem::take(&mut slots[i].lead);
+                    slots[*k].lead.append(&mut lead);
+                }
+                None => kept.push(i),
+            }
+        }
+        order_of = kept;
+    }
+    order_of.sort_by(|a, b| order.compare(&slots[*a].decoded, &slots[*b].decoded));
+
+    let mut kids = head.iter().map(green_element).collect::<Vec<_>>();
+    let count = order_of.len();
+    for (i, moved) in order_of.iter().enumerate() {
+        // when items are removed the last one takes the place of the last item
+        let slot = if i + 1 == count { slots.len() - 1 } else { i };
+        let moved = &slots[*moved];
+        kids.extend(moved.lead.iter().map(green_element));
+        kids.push(item(&slots[slot], moved));
+    }
+    kids.extend(tail.iter().map(green_element));
+    GreenNode::new(TomlKind::Array.into(), kids)
+}
@@ -0,0 +1,313 @@
+use std::cmp::Ordering;
+
+use rowan::TextRange;
+
+use super::array::ItemValue;
+use super::group::groups;
+use super::tables::{entry_cmp, entry_value};
+use super::{
+    heading_segments, match_key, match_table, ArrayOrder, KeyOrder, Matcher, Orders, Pattern,
+    Patterns,
+};
+use crate::diff::doc::trimmed_range;
+use crate::edit::is_array_table;
+use crate::tkn_tree::{path::heading_path, SyntaxNode, SyntaxNodeExtTrait, TomlKind};
+
+/// What kind of item is out of order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ViolationKind {
+    Key,
+    ArrayItem,
+    Table,
+}
+
+/// An item that sorting would move.
+///
+/// `found` and `expected` are positions among the items that are sorted
+/// together, the keys of one table, the items of one array or the tables that
+/// match one pattern. Only the items that have to move are reported, sorting
+/// `[ "b", "c", "a" ]` moves `"a"` from 2 to 0 and the others follow. An
+/// array item that `ArrayOrder::dedup` removes is always reported, `expected`
+/// is where the item it duplicates goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub range: TextRange,
+    pub found: usize,
+    pub expected: usize,
+}
+
+/// The `(found, expected)` positions of the items that are not part of the
+/// longest run already in order.
+///
+/// The sorted order is a permutation of the items, the run in order is the
+/// longest increasing subsequence of the found positions taken in sorted
+/// order.
+fn out_of_order<T>(items: &[T], cmp: impl Fn(&T, &T) -> Ordering) -> Vec<(usize, usize)> {
+    let mut sorted = (0..items.len()).collect::<Vec<_>>();
+    sorted.sort_by(|a, b| cmp(&items[*a], &items[*b]));
+
+    // built from the back so that of the runs of the same length the one
+    // keeping the items that come first once sorted wins, `tails[len]` is the
+    // index into `sorted` starting the run of `len + 1` found positions with
+    // the largest first position
+    let mut tails = Vec::<usize>::default();
+    let mut next = vec![None; sorted.len()];
+    for (i, found) in sorted.iter().enumerate().rev() {
+        let len = tails.partition_point(|t| sorted[*t] > *found);
+        next[i] = len.checked_sub(1).map(|l| tails[l]);
+        if len == tails.len() {
+            tails.push(i);
+        } else {
+            tails[len] = i;
+        }
+    }
+    let mut kept = vec![false; sorted.len()];
+    let mut run = tails.last().copied();
+    while let Some(i) = run {
+        kept[i] = true;
+        run = next[i];
+    }
+
+    sorted
+        .iter()
+        .enumerate()
+        .filter(|(expected, _)| !kept[*expected])
+        .map(|(expected, found)| (*found, expected))
+        .collect::<Vec<_>>()
+}
+
+struct Checker {
+    violations: Vec<Violation>,
+}
+
+impl Checker {
+    fn check<T>(
+        &mut self,
+        kind: ViolationKind,
+        items: &[(T, TextRange)],
+        cmp: impl Fn(&T, &T) -> Ordering,
+    ) {
+        let found = out_of_order(items, |a, b| cmp(&a.0, &b.0));
+        self.violations
+            .extend(found.into_iter().map(|(found, expected)| Violation {
+                kind,
+                range: items[found].1,
+                found,
+                expected,
+            }));
+    }
+
+    /// Checks the keys of each group on its own.
+    fn groups(&mut self, table: &SyntaxNode, order: &KeyOrder) {
+        let kids = table.children_with_tokens().skip(1).collect::<Vec<_>>();
+        for group in groups(&kids) {
+            let keys = group
+                .entries
+                .iter()
+                .map(|e| (e.key.clone(), trimmed_range(e.els[0].as_node().unwrap())))
+                .collect::<Vec<_>>();
+            self.check(ViolationKind::Key, &keys, |a, b| order.compare_keys(a, b));
+        }
+    }
+
+    fn keys(&mut self, table: &SyntaxNode, order: &KeyOrder) {
+        let keys = table
+            .children()
+            .filter(|n| n.kind() == TomlKind::KeyValue)
+            .filter_map(|kv| {
+                let key = kv.children().find(|n| n.kind() == TomlKind::Key)?;
+                Some((key.token_text(), trimmed_range(&kv)))
+            })
+            .collect::<Vec<_>>();
+        self.check(ViolationKind::Key, &keys, |a, b| order.compare_keys(a, b));
+    }
+
+    fn values(&mut self, table: &SyntaxNode, keys: &[Pattern<'_>], orders: &Orders<'_>) {
+        for kv in table.children_with_tokens() {
+            if !match_key(&kv, keys) {
+                continue;
+            }
+            let Some(value) = kv
+                .as_node()
+                .and_then(|kv| kv.children().find(|n| n.kind() == TomlKind::Value))
+                .and_then(|n| n.first_child())
+            else {
+                continue;
+            };
+            if value.kind() == TomlKind::InlineTable {
+                self.keys(&value, orders.key_order(table));
+                continue;
+            }
+            let items = value
+                .children()
+                .filter(|n| n.kind() == TomlKind::ArrayItem)
+                .filter_map(|item| {
+                    let value = item.children().find(|n| n.kind() == TomlKind::Value)?;
+                    Some((ItemValue::new(&value), trimmed_range(&value)))
+                })
+                .collect::<Vec<_>>();
+            let order = orders.array_order(table);
+            if order.dedup {
+                self.dedup(&items, order);
+            } else {
+                self.check(ViolationKind::ArrayItem, &items, |a, b| order.compare(a, b));
+            }
+        }
+    }
+
+    /// Checks the items of an array sorted with `dedup`, every item that
+    /// would be removed is reported with the position of the item it
+    /// duplicates, the others are checked like any array.
+    fn dedup(&mut self, items: &[(ItemValue, TextRange)], order: &ArrayOrder) {
+        let mut kept = Vec::<usize>::default();
+        let mut dropped = Vec::<(usize, usize)>::default();
+        for (i, (item, _)) in items.iter().enumerate() {
+            match kept
+                .iter()
+                .position(|k| order.is_duplicate(&items[*k].0, item))
+            {
+                Some(k) => dropped.push((i, k)),
+                None => kept.push(i),
+            }
+        }
+        let mut sorted = (0..kept.len()).collect::<Vec<_>>();
+        sorted.sort_by(|a, b| order.compare(&items[kept[*a]].0, &items[kept[*b]].0));
+        let mut expected = vec![0; kept.len()];
+        for (pos, k) in sorted.iter().enumerate() {
+            expected[*k] = pos;
+        }
+
+        let found = out_of_order(&kept, |a, b| order.compare(&items[*a].0, &items[*b].0));
+        let moved = found.into_iter().map(|(k, expected)| (kept[k], expected));
+        let removed = dropped.into_iter().map(|(i, k)| (i, expected[k]));
+        self.violations
+            .extend(moved.chain(removed).map(|(found, expected)| Violation {
+                kind: ViolationKind::ArrayItem,
+                range: items[found].1,
+                found,
+                expected,
+            }));
+    }
+}
+
+/// Collects what sorting `root` would move, the keys of tables matching
+/// `heading`, the arrays and inline tables of `heading_key`, the segmented
+/// tables, the entries of arrays of tables of `array_table_key` and the
+/// tables ordered by `orders`.
+pub(crate) fn violations(
+    root: &SyntaxNode,
+    matcher: &Matcher<'_>,
+    orders: &Orders<'_>,
+) -> Vec<Violation> {
+    let mut checker = Checker {
+        violations: Vec::default(),
+    };
+    let tables = root
+        .children()
+        .filter(|n| n.kind() == TomlKind::Table)
+        .collect::<Vec<_>>();
+    let heading = |table: &SyntaxNode| {
+        table
+            .first_child()
+            .map(|h| h.token_text().trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let ranks = orders
+        .tables
+        .iter()
+        .map(|pat| Pattern::new(pat))
+        .collect::<Vec<_>>();
+    let ranked = tables
+        .iter()
+        .filter_map(|t| {
+            let heading = heading(t);
+            let rank = ranks.iter().position(|p| p.is_match(&heading))?;
+            Some((rank, trimmed_range(t)))
+        })
+        .collect::<Vec<_>>();
+    checker.check(ViolationKind::Table, &ranked, |a, b| a.cmp(b));
+
+    for (pat, key) in matcher.array_table_key {
+        let pat = Pattern::new(pat);
+        let mut paths = Vec::<Vec<String>>::default();
+        for table in tables
+            .iter()
+            .filter(|t| is_array_table(t) && pat.is_match(&heading(t)))
+        {
+            let path = heading_path(table);
+            if paths.contains(&path) {
+                continue;
+            }
+            let entries = tables
+                .iter()
+                .filter(|t| is_array_table(t) && heading_path(t) == path)
+                .map(|t| (entry_value(t, key), trimmed_range(t)))
+                .collect::<Vec<_>>();
+            let order = ArrayOrder::default();
+            checker.check(ViolationKind::Table, &entries, |a, b| {
+                entry_cmp(&order, a, b)
+            });
+            paths.push(path);
+        }
+    }
+
+    let patterns = Patterns::new(matcher);
+    for seg in &patterns.segmented {
+        // like sorting each run of matching tables is checked on its own
+        let headings = tables
+            .iter()
+            .map(|t| (heading_segments(heading(t)), trimmed_range(t)))
+            .collect::<Vec<_>>();
+        for run in tables
+            .iter()
+            .zip(headings)
+            .collect::<Vec<_>>()
+            .split(|(t, _)| !seg.is_segment_match(&heading(t)))
+        {
+            let run = run.iter().map(|(_, h)| h.clone()).collect::<Vec<_>>();
+            checker.check(ViolationKind::Table, &run, |a, b| a.cmp(b));
+        }
+    }
+
+    for table in &tables {
+        if match_table(table, &patterns.heading) && orders.groups {
+            checker.groups(table, orders.key_order(table));
+        } else if match_table(table, &patterns.heading) {
+            checker.keys(table, orders.key_order(table));
+        }
+        if match_table(table, &patterns.key_heading) {
+            checker.values(table, &patterns.key, orders);
+        }
+    }
+    checker.violations.sort_by_key(|v| v.range.start());
+    checker.violations
+}
+
+/// Reports every key, array item and table that `sort_toml_items` would move
+/// without building the sorted tree.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{check_sorted, parse_it, Matcher, ViolationKind};
+///
+/// const MATCHER: Matcher<'static> = Matcher {
+///     heading: &["[dependencies]"],
+///     segmented: &[],
+///     heading_key: &[],
+///     array_table_key: &[],
+/// };
+///
+/// let toml = "[dependencies]\nb = \"1\"\nc = \"1\"\na = \"1\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let violations = check_sorted(&root, &MATCHER);
+/// assert_eq!(violations.len(), 1);
+/// assert_eq!(violations[0].kind, ViolationKind::Key);
+/// assert_eq!(&toml[violations[0].range], "a = \"1\"");
+/// assert_eq!((violations[0].found, violations[0].expected), (2, 0));
+/// ```
+pub fn check_sorted(root: &SyntaxNode, matcher: &Matcher<'_>) -> Vec<Violation> {
+    violations(root, matcher, &Orders::default())
+}
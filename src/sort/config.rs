@@ -0,0 +1,396 @@
+use std::{fs, path::Path};
+
+use super::check::violations;
+use super::{
+    order_tables, sort_with, ArrayOrder, KeyOrder, Matcher, Orders, Pattern, Sorted, Violation,
+};
+use crate::common::err::{ParseTomlError, TomlErrorKind, TomlResult};
+use crate::diff::doc::{document, Item, Value};
+use crate::tkn_tree::{parse_it, SyntaxNode};
+
+fn config_err(msg: &str, key: &str) -> ParseTomlError {
+    ParseTomlError::new(msg.into(), TomlErrorKind::InternalParseError(key.into()))
+}
+
+/// An owned `Matcher` that can be built at runtime or loaded from a config file.
+///
+/// The patterns are the same as those of a `Matcher` including the mode
+/// prefixes. A config file looks like
+///
+/// ```toml
+/// heading = [ "[dependencies]", "glob:[*-dependencies]" ]
+/// segmented = [ "dependencies." ]
+/// groups = true
+/// table_order = [ "[package]", "[dependencies]", "[dev-dependencies]" ]
+///
+/// [[heading_key]]
+/// heading = "[workspace]"
+/// key = "members"
+///
+/// [[key_order]]
+/// heading = "exact:[package]"
+/// order = "priority"
+/// priority = [ "name", "version", "edition" ]
+///
+/// [[array_order]]
+/// heading = "[workspace]"
+/// case_insensitive = true
+/// dedup = true
+///
+/// [[array_table_key]]
+/// heading = "[[bin]]"
+/// key = "name"
+/// ```
+///
+/// `order` is one of `lexical`, `case-insensitive`, `natural` or `priority`.
+/// The ordering only applies to tables that `heading` sorts and to inline
+/// tables that `heading_key` sorts, `array_order` to the arrays of
+/// `heading_key`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SortConfig {
+    heading: Vec<String>,
+    segmented: Vec<String>,
+    heading_key: Vec<(String, String)>,
+    key_order: Vec<(String, KeyOrder)>,
+    array_order: Vec<(String, ArrayOrder)>,
+    table_order: Vec<String>,
+    array_table_key: Vec<(String, String)>,
+    groups: bool,
+}
+
+impl SortConfig {
+    pub fn builder() -> SortConfigBuilder {
+        SortConfigBuilder::default()
+    }
+
+    /// Reads the config from the text of a config file.
+    pub fn from_toml(text: &str) -> TomlResult<SortConfig> {
+        let root = parse_it(text)?.syntax();
+        let entries = match document(&root)?.value {
+            Value::Table(entries) => entries,
+            _ => unreachable!("a document is always a table"),
+        };
+
+        let mut builder = SortConfig::builder();
+        for (key, item) in &entries {
+            match key.as_str() {
+                "heading" => {
+                    for pat in strings(item, key)? {
+                        builder = builder.heading(pat);
+                    }
+                }
+                "segmented" => {
+                    for pat in strings(item, key)? {
+                        builder = builder.segmented(pat);
+                    }
+                }
+                "groups" => match item.value {
+                    Value::Bool(groups) => builder = builder.groups(groups),
+                    _ => return Err(config_err("expected a boolean", key)),
+                },
+                "table_order" => {
+                    for pat in strings(item, key)? {
+                        builder = builder.table_order(pat);
+                    }
+                }
+                "heading_key" => {
+                    let tables = match &item.value {
+                        Value::Array(tables) => tables,
+                        _ => return Err(config_err("expected an array of tables", key)),
+                    };
+                    for table in tables {
+                        builder = builder.heading_key(
+                            string(table, key, "heading")?,
+                            string(table, key, "key")?,
+                        );
+                    }
+                }
+                "array_table_key" => {
+                    let tables = match &item.value {
+                        Value::Array(tables) => tables,
+                        _ => return Err(config_err("expected an array of tables", key)),
+                    };
+                    for table in tables {
+                        builder = builder.array_table_key(
+                            string(table, key, "heading")?,
+                            string(table, key, "key")?,
+                        );
+                    }
+                }
+                "key_order" => {
+                    let tables = match &item.value {
+                        Value::Array(tables) => tables,
+                        _ => return Err(config_err("expected an array of tables", key)),
+                    };
+                    for table in tables {
+                        let heading = string(table, key, "heading")?;
+                        let order = match string(table, key, "order")?.as_str() {
+                            "lexical" => KeyOrder::Lexical,
+                            "case-insensitive" => KeyOrder::CaseInsensitive,
+                            "natural" => KeyOrder::Natural,
+                            "priority" => KeyOrder::Priority(match table.get(&["priority"]) {
+                                Some(item) => strings(item, &format!("{}.priority", key))?,
+                                None => Vec::default(),
+                            }),
+                            _ => {
+                                return Err(config_err(
+                                    "unknown key order",
+                                    &format!("{}.order", key),
+                                ))
+                            }
+                        };
+                        builder = builder.key_order(heading, order);
+                    }
+                }
+                "array_order" => {
+                    let tables = match &item.value {
+                        Value::Array(tables) => tables,
+                        _ => return Err(config_err("expected an array of tables", key)),
+                    };
+                    for table in tables {
+                        let order = ArrayOrder {
+                            case_insensitive: boolean(table, key, "case_insensitive")?,
+                            dedup: boolean(table, key, "dedup")?,
+                        };
+                        builder = builder.array_order(string(table, key, "heading")?, order);
+                    }
+                }
+                _ => return Err(config_err("unknown sort config key", key)),
+            }
+        }
+        builder.build()
+    }
+
+    /// Reads the config from the file at `path`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> TomlResult<SortConfig> {
+        SortConfig::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Calls `f` with a `Matcher` borrowing the patterns of this config, for
+    /// the functions that take one.
+    pub fn with_matcher<R>(&self, f: impl FnOnce(&Matcher<'_>) -> R) -> R {
+        let heading = self.heading.iter().map(String::as_str).collect::<Vec<_>>();
+        let segmented = self
+            .segmented
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let heading_key = self
+            .heading_key
+            .iter()
+            .map(|(h, k)| (h.as_str(), k.as_str()))
+            .collect::<Vec<_>>();
+        let array_table_key = self
+            .array_table_key
+            .iter()
+            .map(|(h, k)| (h.as_str(), k.as_str()))
+            .collect::<Vec<_>>();
+        f(&Matcher {
+            heading: &heading,
+            segmented: &segmented,
+            heading_key: &heading_key,
+            array_table_key: &array_table_key,
+        })
+    }
+
+    fn orders(&self) -> Orders<'_> {
+        Orders {
+            keys: self
+                .key_order
+                .iter()
+                .map(|(pat, order)| (Pattern::new(pat), order))
+                .collect(),
+            arrays: self
+                .array_order
+                .iter()
+                .map(|(pat, order)| (Pattern::new(pat), order))
+                .collect(),
+            tables: self.table_order.iter().map(String::as_str).collect(),
+            groups: self.groups,
+        }
+    }
+
+    /// Reports what `sort` would move without sorting, see `check_sorted`.
+    pub fn violations(&self, root: &SyntaxNode) -> Vec<Violation> {
+        let orders = self.orders();
+        self.with_matcher(|matcher| violations(root, matcher, &orders))
+    }
+
+    /// Sorts `root` the way `sort_toml_items` does with this config, keys are
+    /// ordered by the first `key_order` whose heading matches the table and
+    /// arrays by the first `array_order`. When there is a `table_order` the
+    /// tables are put in that order first, then the entries of arrays of
+    /// tables are sorted by their `array_table_key`.
+    pub fn sort(&self, root: &SyntaxNode) -> SyntaxNode {
+        self.sort_in_place(root).root
+    }
+
+    /// Sorts like `sort` replacing only the tables that change, see
+    /// `sort_in_place`.
+    pub fn sort_in_place(&self, root: &SyntaxNode) -> Sorted {
+        let sorted = if self.table_order.is_empty() {
+            root.clone()
+        } else {
+            order_tables(root, &self.table_order)
+        };
+        let orders = self.orders();
+        let sorted = self.with_matcher(|matcher| sort_with(&sorted, matcher, &orders));
+        // the tables moved before are compared to where they were in `root`
+        Sorted::new(root, sorted.root)
+    }
+}
+
+impl From<&Matcher<'_>> for SortConfig {
+    fn from(matcher: &Matcher<'_>) -> Self {
+        SortConfig {
+            heading: matcher.heading.iter().map(|s| s.to_string()).collect(),
+            segmented: matcher.segmented.iter().map(|s| s.to_string()).collect(),
+            heading_key: matcher
+                .heading_key
+                .iter()
+                .map(|(h, k)| (h.to_string(), k.to_string()))
+                .collect(),
+            key_order: Vec::default(),
+            array_order: Vec::default(),
+            table_order: Vec::default(),
+            array_table_key: matcher
+                .array_table_key
+                .iter()
+                .map(|(h, k)| (h.to_string(), k.to_string()))
+                .collect(),
+            groups: false,
+        }
+    }
+}
+
+fn string(table: &Item, key: &str, field: &str) -> TomlResult<String> {
+    match table.get(&[field]).map(|i| &i.value) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        _ => Err(config_err(
+            "expected a string",
+            &format!("{}.{}", key, field),
+        )),
+    }
+}
+
+/// A missing field is `false`.
+fn boolean(table: &Item, key: &str, field: &str) -> TomlResult<bool> {
+    match table.get(&[field]).map(|i| &i.value) {
+        Some(Value::Bool(b)) => Ok(*b),
+        None => Ok(false),
+        _ => Err(config_err(
+            "expected a boolean",
+            &format!("{}.{}", key, field),
+        )),
+    }
+}
+
+fn strings(item: &Item, key: &str) -> TomlResult<Vec<String>> {
+    let items = match &item.value {
+        Value::Array(items) => items,
+        _ => return Err(config_err("expected an array of strings", key)),
+    };
+    items
+        .iter()
+        .map(|item| match &item.value {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(config_err("expected an array of strings", key)),
+        })
+        .collect()
+}
+
+/// Builds a `SortConfig` one pattern at a time.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, SortConfig, SyntaxNodeExtTrait};
+///
+/// let config = SortConfig::builder()
+///     .heading("glob:[*dependencies]")
+///     .heading_key("[workspace]", "members")
+///     .build()
+///     .unwrap();
+///
+/// let root = parse_it("[dependencies]\nb = \"1\"\na = \"1\"\n").unwrap().syntax();
+/// assert_eq!(config.sort(&root).token_text(), "[dependencies]\na = \"1\"\nb = \"1\"\n");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SortConfigBuilder {
+    config: SortConfig,
+}
+
+impl SortConfigBuilder {
+    /// Sort the keys of tables with a matching heading.
+    pub fn heading<S: Into<String>>(mut self, pat: S) -> Self {
+        self.config.heading.push(pat.into());
+        self
+    }
+
+    /// Sort the segmented tables that match by their full path, a table comes
+    /// before the tables nested in it. Each run of matching tables between
+    /// tables that do not match is sorted on its own, see `Matcher::segmented`.
+    pub fn segmented<S: Into<String>>(mut self, pat: S) -> Self {
+        self.config.segmented.push(pat.into());
+        self
+    }
+
+    /// Sort the array or inline table at `key` in tables with a matching heading.
+    pub fn heading_key<H: Into<String>, K: Into<String>>(mut self, heading: H, key: K) -> Self {
+        self.config.heading_key.push((heading.into(), key.into()));
+        self
+    }
+
+    /// Sort the keys of a table within each group instead of all at once. A
+    /// group ends at a blank line or a comment on a line of its own, the
+    /// comments that start a group stay at its top.
+    pub fn groups(mut self, groups: bool) -> Self {
+        self.config.groups = groups;
+        self
+    }
+
+    /// Order the keys of tables with a matching heading by `order`, the first
+    /// matching pattern wins.
+    pub fn key_order<S: Into<String>>(mut self, heading: S, order: KeyOrder) -> Self {
+        self.config.key_order.push((heading.into(), order));
+        self
+    }
+
+    /// Order the arrays sorted by `heading_key` in tables with a matching
+    /// heading by `order`, the first matching pattern wins.
+    pub fn array_order<S: Into<String>>(mut self, heading: S, order: ArrayOrder) -> Self {
+        self.config.array_order.push((heading.into(), order));
+        self
+    }
+
+    /// Put tables matching `heading` after those matching the patterns given
+    /// before it, see `order_tables`.
+    pub fn table_order<S: Into<String>>(mut self, heading: S) -> Self {
+        self.config.table_order.push(heading.into());
+        self
+    }
+
+    /// Sort the entries of arrays of tables with a matching heading by the
+    /// value of `key`, see `sort_array_tables`.
+    pub fn array_table_key<H: Into<String>, K: Into<String>>(mut self, heading: H, key: K) -> Self {
+        self.config
+            .array_table_key
+            .push((heading.into(), key.into()));
+        self
+    }
+
+    /// Returns an error when one of the `regex:` patterns is invalid.
+    pub fn build(self) -> TomlResult<SortConfig> {
+        let config = self.config;
+        config
+            .heading
+            .iter()
+            .chain(&config.segmented)
+            .chain(config.heading_key.iter().flat_map(|(h, k)| vec![h, k]))
+            .chain(config.key_order.iter().map(|(h, _)| h))
+            .chain(config.array_order.iter().map(|(h, _)| h))
+            .chain(&config.table_order)
+            .chain(config.array_table_key.iter().map(|(h, _)| h))
+            .try_for_each(|pat| Pattern::check(pat))?;
+        Ok(config)
+    }
+}
@@ -0,0 +1,89 @@
+use rowan::GreenNode;
+
+use super::KeyOrder;
+use crate::edit::{green_element, trailing_ws, with_trailing_ws};
+use crate::tkn_tree::{SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind};
+
+/// A key value pair and the comment after it on the same line.
+pub(crate) struct Entry {
+    pub(crate) key: String,
+    pub(crate) els: Vec<SyntaxElement>,
+}
+
+/// A run of key value pairs that are not split by a blank line or a comment
+/// on a line of its own, `header` holds the comments that start the group.
+pub(crate) struct Group {
+    pub(crate) header: Vec<SyntaxElement>,
+    pub(crate) entries: Vec<Entry>,
+}
+
+/// Splits the children of a table after its heading into groups.
+pub(crate) fn groups(kids: &[SyntaxElement]) -> Vec<Group> {
+    let mut groups = vec![Group {
+        header: Vec::default(),
+        entries: Vec::default(),
+    }];
+    let mut prev_ws = String::from("\n");
+    for el in kids {
+        let group = groups.last_mut().unwrap();
+        let same_line = !prev_ws.contains('\n');
+        let blank_line = prev_ws.matches('\n').count() > 1;
+        match el.kind() {
+            TomlKind::KeyValue => {
+                let key = el
+                    .as_node()
+                    .and_then(|kv| kv.children().find(|n| n.kind() == TomlKind::Key))
+                    .map(|k| k.token_text())
+                    .unwrap_or_default();
+                let entry = Entry {
+                    key,
+                    els: vec![el.clone()],
+                };
+                if blank_line && !group.entries.is_empty() {
+                    groups.push(Group {
+                        header: Vec::default(),
+                        entries: vec![entry],
+                    });
+                } else {
+                    group.entries.push(entry);
+                }
+            }
+            _ if same_line && !group.entries.is_empty() => {
+                group.entries.last_mut().unwrap().els.push(el.clone());
+            }
+            _ if group.entries.is_empty() => group.header.push(el.clone()),
+            _ => groups.push(Group {
+                header: vec![el.clone()],
+                entries: Vec::default(),
+            }),
+        }
+        prev_ws = trailing_ws(el);
+    }
+    groups
+}
+
+/// Sorts the keys of `table` within each group, the comments that start a
+/// group and the blank lines between groups stay where they are.
+pub(crate) fn sort_groups(table: &SyntaxNode, order: &KeyOrder) -> GreenNode {
+    let kids = table.children_with_tokens().collect::<Vec<_>>();
+    let mut new_kids = kids.iter().take(1).map(green_element).collect::<Vec<_>>();
+    for group in groups(&kids[1.min(kids.len())..]) {
+        new_kids.extend(group.header.iter().map(green_element));
+
+        let mut sorted = (0..group.entries.len()).collect::<Vec<_>>();
+        sorted.sort_by(|a, b| order.compare_keys(&group.entries[*a].key, &group.entries[*b].key));
+        for (slot, idx) in sorted.iter().enumerate() {
+            // the whitespace after each entry stays with its place
+            let ws = trailing_ws(group.entries[slot].els.last().unwrap());
+            let els = &group.entries[*idx].els;
+            for (i, el) in els.iter().enumerate() {
+                if i + 1 == els.len() {
+                    new_kids.push(with_trailing_ws(el, &ws));
+                } else {
+                    new_kids.push(green_element(el));
+                }
+            }
+        }
+    }
+    GreenNode::new(TomlKind::Table.into(), new_kids)
+}
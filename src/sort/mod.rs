@@ -1,75 +1,294 @@
 //! Sort the given toml file based on SyntaxElements.
 //!
 //! Using a `Matcher` to specify the tables and values that have items that should be sorted
-//! call `sort_toml_items` then compare the resulting tree using `SyntaxNodeExtTrait::deep_eq`.
+//! call `sort_toml_items` then compare the resulting tree using `SyntaxNodeExtTrait::deep_eq`,
+//! call `sort_in_place` to also get the ranges of the tables that changed,
+//! or call `check_sorted` to find what is out of order without sorting.
 
 use std::cmp::Ordering;
 
-use rowan::{GreenNode, GreenNodeBuilder};
-
-use super::tkn_tree::{SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind};
+use rowan::{GreenNode, NodeOrToken, TextRange};
+
+use super::common::err::TomlResult;
+use super::edit::{green_element, replace_children};
+use super::tkn_tree::{path::split_key, SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind};
+
+mod array;
+mod check;
+mod config;
+mod group;
+mod order;
+mod pattern;
+mod tables;
+
+use array::sort_array;
+pub use array::ArrayOrder;
+pub use check::{check_sorted, Violation, ViolationKind};
+pub use config::{SortConfig, SortConfigBuilder};
+use group::sort_groups;
+pub use order::KeyOrder;
+pub(crate) use pattern::Pattern;
+pub use tables::{order_tables, sort_array_tables, CARGO_TABLE_ORDER};
 
 /// Each `Matcher` field when matched to a heading or key token
-/// will be matched with `.contains()`.
+/// will be matched with `.contains()`, unless the pattern starts with
+/// one of the prefixes that picks another mode.
+///
+/// * `exact:[dependencies]` matches only that heading.
+/// * `prefix:[target.` matches headings that start with it.
+/// * `glob:[*-dependencies]` matches with `*` as any run of characters and
+///   `?` as any single one, there are no character classes so braces are
+///   plain characters.
+/// * `regex:^\[.*dependencies\]$` matches with a regular expression.
+///
+/// Headings are matched with their braces, `segmented` patterns with a mode
+/// are matched to the heading without them and `heading_key` keys to the key
+/// as written.
+///
+/// Keys are ordered lexically and arrays by value, a `KeyOrder` or
+/// `ArrayOrder` for the tables of some headings is set on a `SortConfig`.
+///
+/// The patterns are compiled once for each sort. A `regex:` pattern that is
+/// not a valid regular expression matches nothing, `Matcher::check` reports
+/// it and `SortConfigBuilder::build` rejects it.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, sort_toml_items, Matcher, SyntaxNodeExtTrait};
+///
+/// const MATCHER: Matcher<'static> = Matcher {
+///     heading: &["glob:[*dependencies]"],
+///     segmented: &[],
+///     heading_key: &[],
+///     array_table_key: &[],
+/// };
+///
+/// let toml = "[dev-dependencies]\nb = \"1\"\na = \"1\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let sorted = sort_toml_items(&root, &MATCHER);
+/// assert_eq!(sorted.token_text(), "[dev-dependencies]\na = \"1\"\nb = \"1\"\n");
+/// ```
 pub struct Matcher<'a> {
     /// Toml headings with braces `[heading]`.
     pub heading: &'a [&'a str],
-    /// Toml segmented heading without braces.
+    /// Toml segmented heading without braces, the matching tables are sorted
+    /// by their full path. A table that does not match stays where it is and
+    /// the matching tables on each side of it are sorted on their own, so
+    /// siblings are only ordered within a run and `[dependencies.b]`,
+    /// `[hello]`, `[dependencies.a]` is left as it is.
     pub segmented: &'a [&'a str],
     /// Toml heading with braces `[heading]` and the key
-    /// of the array to sort.
+    /// of the array or inline table to sort.
     pub heading_key: &'a [(&'a str, &'a str)],
+    /// Toml array of tables heading with braces `[[heading]]` and the key
+    /// whose value orders its entries, see `sort_array_tables`.
+    pub array_table_key: &'a [(&'a str, &'a str)],
+}
+
+impl Matcher<'_> {
+    /// Returns an error for the first `regex:` pattern that is not a valid
+    /// regular expression, those never match anything.
+    pub fn check(&self) -> TomlResult<()> {
+        let (head, key): (Vec<_>, Vec<_>) = self.heading_key.iter().cloned().unzip();
+        self.heading
+            .iter()
+            .chain(self.segmented)
+            .chain(&head)
+            .chain(&key)
+            .chain(self.array_table_key.iter().map(|(h, _)| h))
+            .try_for_each(|pat| Pattern::check(pat))
+    }
+}
+
+/// The patterns of a `Matcher` compiled for one sort.
+pub(crate) struct Patterns<'a> {
+    pub(crate) heading: Vec<Pattern<'a>>,
+    pub(crate) segmented: Vec<Pattern<'a>>,
+    /// The headings of `heading_key`.
+    pub(crate) key_heading: Vec<Pattern<'a>>,
+    /// The keys of `heading_key`.
+    pub(crate) key: Vec<Pattern<'a>>,
+}
+
+impl<'a> Patterns<'a> {
+    pub(crate) fn new(matcher: &Matcher<'a>) -> Self {
+        let (head, key): (Vec<_>, Vec<_>) = matcher.heading_key.iter().cloned().unzip();
+        Patterns {
+            heading: Pattern::all(matcher.heading),
+            segmented: Pattern::all(matcher.segmented),
+            key_heading: head.into_iter().map(Pattern::new).collect(),
+            key: key.into_iter().map(Pattern::new).collect(),
+        }
+    }
 }
 
-fn split_seg_last<S: AsRef<str>>(s: S) -> String {
+/// The unquoted segments of a heading written with its braces, headings sort
+/// by these so a table comes right before the tables nested in it.
+pub(crate) fn heading_segments<S: AsRef<str>>(s: S) -> Vec<String> {
     let open_close: &[char] = &['[', ']'];
-    let heading = s.as_ref();
-
-    heading
-        .replace(open_close, "")
-        .split('.')
-        .last()
-        .map(ToString::to_string)
-        .unwrap()
+    split_key(s.as_ref().trim().trim_matches(open_close))
 }
 
-pub fn sort_toml_items(root: &SyntaxNode, matcher: &Matcher<'_>) -> SyntaxNode {
-    let mut builder = GreenNodeBuilder::new();
-    builder.start_node(TomlKind::Root.into());
-
-    for ele in sorted_tables_with_tokens(root, matcher.segmented) {
-        match ele.kind() {
-            TomlKind::Table => {
-                // for [workspace] members = ...
-                // this is heading and members is key.
-                let (head, key): (Vec<_>, Vec<_>) = matcher.heading_key.iter().cloned().unzip();
-                let node = ele.as_node().unwrap();
-                if match_table(node, matcher.heading) {
-                    add_sorted_table(node, &mut builder)
-                } else if match_table(node, &head) {
-                    add_table_sort_items(node, &mut builder, &key)
-                } else {
-                    add_element(ele, &mut builder)
-                }
+/// A sorted tree along with what sorting changed.
+///
+/// The tables that did not change share their green nodes with the tree that
+/// was sorted, `touched` holds the ranges in `root` of the tables that were
+/// sorted or moved, in document order.
+#[derive(Clone, Debug)]
+pub struct Sorted {
+    pub root: SyntaxNode,
+    pub touched: Vec<TextRange>,
+}
+
+impl Sorted {
+    /// Compares the tables of `root` to the tables in the same place in `old`.
+    pub(crate) fn new(old: &SyntaxNode, root: SyntaxNode) -> Self {
+        let mut old_tables = old.children().filter(|n| n.kind() == TomlKind::Table);
+        let mut touched = Vec::default();
+        for table in root.children().filter(|n| n.kind() == TomlKind::Table) {
+            match old_tables.next() {
+                Some(old) if old.green() == table.green() => {}
+                _ => touched.push(table.text_range()),
             }
-            _ => add_element(ele, &mut builder),
         }
+        Sorted { root, touched }
+    }
+}
+
+/// Sorts the tables, keys and values of `root` that `matcher` picks.
+///
+/// A `regex:` pattern that does not compile matches nothing, check the
+/// matcher with `Matcher::check` first to report it.
+pub fn sort_toml_items(root: &SyntaxNode, matcher: &Matcher<'_>) -> SyntaxNode {
+    sort_in_place(root, matcher).root
+}
+
+/// Sorts like `sort_toml_items` replacing only the tables that change, when
+/// nothing is out of order `root` itself is returned.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, sort_in_place, Matcher, SyntaxNodeExtTrait};
+///
+/// const MATCHER: Matcher<'static> = Matcher {
+///     heading: &["[dependencies]"],
+///     segmented: &[],
+///     heading_key: &[],
+///     array_table_key: &[],
+/// };
+///
+/// let toml = "[package]\nname = \"a\"\n\n[dependencies]\nb = \"1\"\na = \"1\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let sorted = sort_in_place(&root, &MATCHER);
+/// assert_eq!(sorted.touched.len(), 1);
+/// assert_eq!(
+///     &sorted.root.token_text()[sorted.touched[0]],
+///     "[dependencies]\na = \"1\"\nb = \"1\"\n"
+/// );
+/// ```
+pub fn sort_in_place(root: &SyntaxNode, matcher: &Matcher<'_>) -> Sorted {
+    sort_with(root, matcher, &Orders::default())
+}
+
+/// The orderings a `SortConfig` adds to a `Matcher`, the first heading
+/// pattern that matches a table picks the ordering of its keys and arrays.
+#[derive(Default)]
+pub(crate) struct Orders<'a> {
+    pub(crate) keys: Vec<(Pattern<'a>, &'a KeyOrder)>,
+    pub(crate) arrays: Vec<(Pattern<'a>, &'a ArrayOrder)>,
+    /// The patterns of `order_tables`.
+    pub(crate) tables: Vec<&'a str>,
+    /// Sort keys within each group of a table, see `SortConfigBuilder::groups`.
+    pub(crate) groups: bool,
+}
+
+impl Orders<'_> {
+    pub(crate) fn key_order(&self, table: &SyntaxNode) -> &KeyOrder {
+        self.keys
+            .iter()
+            .find(|(pat, _)| match_table(table, std::slice::from_ref(pat)))
+            .map(|(_, order)| *order)
+            .unwrap_or(&KeyOrder::Lexical)
+    }
+
+    pub(crate) fn array_order(&self, table: &SyntaxNode) -> &ArrayOrder {
+        const DEFAULT: &ArrayOrder = &ArrayOrder {
+            case_insensitive: false,
+            dedup: false,
+        };
+        self.arrays
+            .iter()
+            .find(|(pat, _)| match_table(table, std::slice::from_ref(pat)))
+            .map(|(_, order)| *order)
+            .unwrap_or(DEFAULT)
+    }
+}
+
+/// Sorts like `sort_toml_items` with the keys and arrays of each table ordered
+/// by `orders`.
+pub(crate) fn sort_with(root: &SyntaxNode, matcher: &Matcher<'_>, orders: &Orders<'_>) -> Sorted {
+    let patterns = Patterns::new(matcher);
+    let mut entries = root.clone();
+    for (heading, key) in matcher.array_table_key {
+        entries = sort_array_tables(&entries, heading, key);
+    }
+    let kids = entries.children_with_tokens().collect::<Vec<_>>();
+    let moved = sorted_tables_with_tokens(&entries, &patterns.segmented).collect::<Vec<_>>();
+
+    let mut changed = moved != kids;
+    let greens = moved
+        .iter()
+        .map(|el| match el.as_node() {
+            Some(table) if table.kind() == TomlKind::Table => {
+                let green = sort_table(table, &patterns, orders);
+                changed |= green != *table.green();
+                NodeOrToken::Node(green)
+            }
+            _ => green_element(el),
+        })
+        .collect::<Vec<_>>();
+    let sorted = if changed {
+        replace_children(&entries, greens)
+    } else {
+        entries
+    };
+    Sorted::new(root, sorted)
+}
+
+/// The green node of `table` with its keys and values sorted, the parts that
+/// do not move are shared with `table`.
+fn sort_table(table: &SyntaxNode, patterns: &Patterns<'_>, orders: &Orders<'_>) -> GreenNode {
+    // for [workspace] members = ...
+    // this is heading and members is key.
+    let node = if match_table(table, &patterns.key_heading) {
+        SyntaxNode::new_root(sort_table_values(table, &patterns.key, orders))
+    } else {
+        table.clone()
+    };
+    if match_table(&node, &patterns.heading) && orders.groups {
+        sort_groups(&node, orders.key_order(&node))
+    } else if match_table(&node, &patterns.heading) {
+        sorted_table(&node, orders.key_order(&node))
+    } else {
+        node.green().clone()
     }
-    builder.finish_node();
-    let green: GreenNode = builder.finish();
-    SyntaxNode::new_root(green)
 }
 
-fn match_table(node: &SyntaxNode, headings: &[&str]) -> bool {
+pub(crate) fn match_table(node: &SyntaxNode, headings: &[Pattern<'_>]) -> bool {
     match node.first_child().map(|n| n.kind()) {
-        Some(TomlKind::Heading) => headings.iter().any(|h| node.token_text().contains(h)),
+        Some(TomlKind::Heading) => {
+            let heading = node.first_child().unwrap().token_text();
+            headings.iter().any(|h| h.is_match(heading.trim()))
+        }
         _ => false,
     }
 }
 
 fn sorted_tables_with_tokens(
     root: &SyntaxNode,
-    segmented: &[&str],
+    segmented: &[Pattern<'_>],
 ) -> impl Iterator<Item = SyntaxElement> {
     let kids = root.children_with_tokens().collect::<Vec<_>>();
     let pos = root
@@ -107,47 +326,41 @@ fn sorted_tables_with_tokens(
     }
 
     for seg in segmented {
-        #[rustfmt::skip]
-        tables.sort_by(|chunk, other| {
-            let chunk_matches_heading = chunk.0.as_ref()
-                .map(|head| head.contains(&format!("[{}", seg))) == Some(true);
-            let other_matches_heading = other.0.as_ref()
-                .map(|head| head.contains(&format!("[{}", seg))) == Some(true);
-
-            if chunk_matches_heading && other_matches_heading {
-                chunk
-                    .0
-                    .as_ref()
-                    .map(split_seg_last)
-                    .cmp(&other.0.as_ref().map(split_seg_last))
-            } else {
-                Ordering::Equal
-            }
-        });
+        let matches = |heading: &Option<String>| {
+            heading.as_ref().map(|head| seg.is_segment_match(head)) == Some(true)
+        };
+        // each run of matching tables is sorted on its own by the full path
+        // of the headings, a table that does not match ends the run
+        let mut start = 0;
+        while start < tables.len() {
+            let len = tables[start..]
+                .iter()
+                .take_while(|chunk| matches(&chunk.0))
+                .count();
+            tables[start..start + len].sort_by_key(|chunk| chunk.0.as_ref().map(heading_segments));
+            start += len.max(1);
+        }
     }
 
-    tables.into_iter().map(|p| p.1).flatten()
+    tables.into_iter().flat_map(|p| p.1)
 }
 
-fn add_sorted_table(node: &SyntaxNode, builder: &mut GreenNodeBuilder) {
-    builder.start_node(node.kind().into());
+fn sorted_table(node: &SyntaxNode, order: &KeyOrder) -> GreenNode {
+    let heading = match node.first_child() {
+        Some(heading) => NodeOrToken::Node(heading.green().clone()),
+        None => unreachable!("table without heading"),
+    };
 
-    if let Some(heading) = node.first_child() {
-        add_node(&heading, builder);
-    } else {
-        unreachable!("table without heading")
-    }
-
-    // skip the table heading we just added
+    // skip the table heading
     let kv = node.children_with_tokens().skip(1).collect::<Vec<_>>();
-    for ele in sort_key_value(&kv) {
-        add_element(ele, builder);
-    }
-
-    builder.finish_node();
+    let kids = Some(heading)
+        .into_iter()
+        .chain(sort_key_value(&kv, order).iter().map(green_element))
+        .collect::<Vec<_>>();
+    GreenNode::new(node.kind().into(), kids)
 }
 
-fn sort_key_value(kv: &[SyntaxElement]) -> Vec<SyntaxElement> {
+fn sort_key_value(kv: &[SyntaxElement], order: &KeyOrder) -> Vec<SyntaxElement> {
     let pos = kv
         .iter()
         .enumerate()
@@ -195,245 +408,117 @@ fn sort_key_value(kv: &[SyntaxElement]) -> Vec<SyntaxElement> {
         keys.push((None, &kv[start..]))
     }
 
-    keys.sort_by(|chunk, other| {
-        if chunk.0.is_none() || other.0.is_none() {
-            return Ordering::Equal;
-        }
-        chunk.0.cmp(&other.0)
+    keys.sort_by(|chunk, other| match (&chunk.0, &other.0) {
+        (Some(a), Some(b)) => order.compare_keys(a, b),
+        _ => Ordering::Equal,
     });
-    keys.into_iter().map(|p| p.1).flatten().cloned().collect()
+    keys.into_iter().flat_map(|p| p.1).cloned().collect()
 }
 
-fn match_key(node: &SyntaxElement, keys: &[&str]) -> bool {
+fn match_key(node: &SyntaxElement, keys: &[Pattern<'_>]) -> bool {
     match node
         .as_node()
-        .map(|n| n.first_child().map(|n| n.kind()))
-        .flatten()
+        .and_then(|n| n.first_child().map(|n| n.kind()))
     {
         Some(TomlKind::Key) => keys.iter().any(|h| {
-            node.as_node()
-                .unwrap()
-                .first_child()
-                .unwrap()
-                .token_text()
-                .contains(h)
-                && node
-                    .as_node()
+            h.is_match(
+                node.as_node()
                     .unwrap()
-                    .children()
-                    .find(|n| n.kind() == TomlKind::Value)
-                    .map(|n| n.first_child().map(|n| n.kind() == TomlKind::Array))
-                    .flatten()
-                    == Some(true)
+                    .first_child()
+                    .unwrap()
+                    .token_text()
+                    .trim(),
+            ) && node
+                .as_node()
+                .unwrap()
+                .children()
+                .find(|n| n.kind() == TomlKind::Value)
+                .and_then(|n| n.first_child())
+                .map(|n| matches!(n.kind(), TomlKind::Array | TomlKind::InlineTable))
+                == Some(true)
         }),
         _ => false,
     }
 }
 
-fn add_table_sort_items(node: &SyntaxNode, builder: &mut GreenNodeBuilder, key: &[&str]) {
-    builder.start_node(node.kind().into());
-
-    if let Some(heading) = node.first_child() {
-        add_node(&heading, builder);
-    } else {
-        unreachable!("table without heading")
-    }
-
-    for ele in node.children_with_tokens().skip(1) {
-        if match_key(&ele, key) {
+/// Sorts the arrays and inline tables of `node` at the keys in `key`.
+fn sort_table_values(node: &SyntaxNode, key: &[Pattern<'_>], orders: &Orders<'_>) -> GreenNode {
+    let kids = node
+        .children_with_tokens()
+        .map(|ele| {
+            if !match_key(&ele, key) {
+                return green_element(&ele);
+            }
             // this is a `KeyValue` node
-            builder.start_node(ele.kind().into());
-            for el in ele.as_node().unwrap().children_with_tokens() {
-                match el {
-                    SyntaxElement::Node(n) => match n.kind() {
-                        TomlKind::Value => {
-                            builder.start_node(TomlKind::Value.into());
-                            if n.first_child().map(|n| n.kind()) == Some(TomlKind::Array) {
-                                // the node type like TomlKind::Array
-                                builder.start_node(TomlKind::Array.into());
-                                builder
-                                    .token(TomlKind::OpenBrace.into(), rowan::SmolStr::from("["));
-                                for (end, sorted) in sort_items(n.first_child().unwrap()) {
-                                    add_array_items(sorted, builder, end);
+            let kids = ele
+                .as_node()
+                .unwrap()
+                .children_with_tokens()
+                .map(|el| match &el {
+                    SyntaxElement::Node(n) if n.kind() == TomlKind::Value => {
+                        let kids = n
+                            .children_with_tokens()
+                            .map(|kid| match kid.as_node() {
+                                Some(arr) if arr.kind() == TomlKind::Array => {
+                                    NodeOrToken::Node(sort_array(arr, orders.array_order(node)))
                                 }
-                                builder
-                                    .token(TomlKind::CloseBrace.into(), rowan::SmolStr::from("]"));
-                                builder.finish_node();
-                            }
-                            builder.finish_node();
-                        }
-                        _ => add_node(&n, builder),
-                    },
-                    SyntaxElement::Token(t) => builder.token(t.kind().into(), t.text().clone()),
-                }
-            }
-            builder.finish_node();
-        } else {
-            add_element(ele, builder);
-        }
-    }
-
-    builder.finish_node();
-}
-
-fn sort_items(node: SyntaxNode) -> Vec<(bool, SyntaxElement)> {
-    // node is TomlKind::Array
-    let children = node
-        .children_with_tokens()
-        .filter(|n| {
-            let n = n.as_token().map(|n| n.kind());
-            n != Some(TomlKind::CloseBrace) && n != Some(TomlKind::OpenBrace)
-        })
-        .collect::<Vec<_>>();
-
-    let pos = children
-        .iter()
-        .enumerate()
-        .filter(|(_, n)| n.as_node().map(|n| n.kind()) == Some(TomlKind::ArrayItem))
-        .map(|(i, n)| {
-            (
-                i,
-                n.as_node()
-                    .unwrap()
-                    .children()
-                    .find(|n| n.kind() == TomlKind::Value)
-                    .map(|n| n.token_text()),
-            )
+                                Some(obj) if obj.kind() == TomlKind::InlineTable => {
+                                    NodeOrToken::Node(sort_inline_table(
+                                        obj,
+                                        orders.key_order(node),
+                                    ))
+                                }
+                                _ => green_element(&kid),
+                            })
+                            .collect::<Vec<_>>();
+                        NodeOrToken::Node(GreenNode::new(TomlKind::Value.into(), kids))
+                    }
+                    _ => green_element(&el),
+                })
+                .collect::<Vec<_>>();
+            NodeOrToken::Node(GreenNode::new(TomlKind::KeyValue.into(), kids))
         })
         .collect::<Vec<_>>();
-
-    let mut sorted = Vec::default();
-    let mut current = 0;
-    for (idx, key) in pos {
-        let next_is_whitespace = children
-            .get(idx + 1)
-            .map(|el| el.as_token().map(|t| t.kind()) == Some(TomlKind::Whitespace))
-            == Some(true);
-
-        let idx = if next_is_whitespace { idx + 1 } else { idx };
-        sorted.push((key, &children[current..=idx]));
-        current = idx + 1;
-    }
-    if current != children.len() {
-        sorted.push((None, &children[current..]))
-    }
-    sorted.sort_by(|chunk, other| {
-        if chunk.0.is_none() {
-            return Ordering::Equal;
-        }
-        if other.0.is_none() {
-            return Ordering::Equal;
-        }
-        chunk.0.cmp(&other.0)
-    });
-    let end = sorted.len() - 1;
-    sorted
-        .into_iter()
-        .flat_map(|p| p.1)
-        .cloned()
-        .enumerate()
-        .map(|(i, el)| (i == end, el))
-        .collect()
+    GreenNode::new(node.kind().into(), kids)
 }
 
-fn add_node(node: &SyntaxNode, builder: &mut GreenNodeBuilder) {
-    builder.start_node(node.kind().into());
-
-    for kid in node.children_with_tokens() {
-        match kid {
-            SyntaxElement::Node(n) => add_node(&n, builder),
-            SyntaxElement::Token(t) => builder.token(t.kind().into(), t.text().clone()),
-        }
-    }
-
-    builder.finish_node();
-}
+/// Orders the keys of an `InlineTable` by `order`, the commas and whitespace
+/// between the pairs stay where they were so spacing and a trailing comma are
+/// kept.
+fn sort_inline_table(node: &SyntaxNode, order: &KeyOrder) -> GreenNode {
+    let key = |kv: &SyntaxNode| {
+        kv.children()
+            .find(|n| n.kind() == TomlKind::Key)
+            .map(|n| n.token_text())
+            .unwrap_or_default()
+    };
+    let mut kvs = node
+        .children()
+        .filter(|n| n.kind() == TomlKind::KeyValue)
+        .collect::<Vec<_>>();
+    kvs.sort_by(|a, b| order.compare_keys(&key(a), &key(b)));
+    let mut kvs = kvs.into_iter();
 
-// TODO This for now alters the tokens, it checks if each element has a comma and space
-// and removes it from the last element if it has comma and space ????
-fn add_array_items(node: SyntaxElement, builder: &mut GreenNodeBuilder, end: bool) {
-    match node {
-        SyntaxElement::Node(node) => {
-            if node.kind() == TomlKind::ArrayItem && !end && !node.token_text().contains("\n ") {
-                match node
+    let kids = node
+        .children_with_tokens()
+        .map(|el| match &el {
+            SyntaxElement::Node(n) if n.kind() == TomlKind::KeyValue => {
+                let sorted = kvs.next().unwrap();
+                // the padding of the slot with the pair that moves into it
+                let lead = n
                     .children_with_tokens()
-                    .map(|el| el.kind())
-                    .collect::<Vec<TomlKind>>()
-                    .as_slice()
-                {
-                    [.., TomlKind::Comma, TomlKind::Whitespace] => {
-                        // this is a normal ArrayItem with comma and space
-                        builder.start_node(node.kind().into());
-                        for kid in node.children_with_tokens() {
-                            match kid {
-                                SyntaxElement::Node(n) => add_node(&n, builder),
-                                SyntaxElement::Token(t) => {
-                                    builder.token(t.kind().into(), t.text().clone())
-                                }
-                            }
-                        }
-                        builder.finish_node();
-                    }
-                    [.., _, _] | [_] | [] => {
-                        // these have no comma or space and aren't the last ele so add comma...
-                        builder.start_node(node.kind().into());
-                        for kid in node.children_with_tokens() {
-                            match kid {
-                                SyntaxElement::Node(n) => add_node(&n, builder),
-                                SyntaxElement::Token(t) => {
-                                    builder.token(t.kind().into(), t.text().clone())
-                                }
-                            }
-                        }
-                        builder.token(TomlKind::Comma.into(), rowan::SmolStr::from(","));
-                        builder.token(TomlKind::Whitespace.into(), rowan::SmolStr::from(" "));
-                        builder.finish_node();
-                    }
-                }
-            } else if end && !node.token_text().contains("\n ") {
-                // removes last comma
-                builder.start_node(node.kind().into());
-                for kid in node.children_with_tokens() {
-                    match kid {
-                        SyntaxElement::Node(n) => add_node(&n, builder),
-                        SyntaxElement::Token(t) => {
-                            if t.kind() == TomlKind::Comma {
-                                builder.finish_node();
-                                return;
-                            }
-                            builder.token(t.kind().into(), t.text().clone())
-                        }
-                    }
-                }
-                builder.finish_node();
-            } else {
-                // we dont care what sequence of tokens are here just add em
-                builder.start_node(node.kind().into());
-                for kid in node.children_with_tokens() {
-                    match kid {
-                        SyntaxElement::Node(n) => add_node(&n, builder),
-                        SyntaxElement::Token(t) => builder.token(t.kind().into(), t.text().clone()),
-                    }
-                }
-                builder.finish_node();
-            }
-        }
-        SyntaxElement::Token(t) => builder.token(t.kind().into(), t.text().clone()),
-    }
-}
-
-fn add_element(node: SyntaxElement, builder: &mut GreenNodeBuilder) {
-    match node {
-        SyntaxElement::Node(node) => {
-            builder.start_node(node.kind().into());
-            for kid in node.children_with_tokens() {
-                match kid {
-                    SyntaxElement::Node(n) => add_node(&n, builder),
-                    SyntaxElement::Token(t) => builder.token(t.kind().into(), t.text().clone()),
-                }
+                    .take_while(|el| el.kind() == TomlKind::Whitespace);
+                let rest = sorted
+                    .children_with_tokens()
+                    .skip_while(|el| el.kind() == TomlKind::Whitespace);
+                let kids = lead
+                    .chain(rest)
+                    .map(|el| green_element(&el))
+                    .collect::<Vec<_>>();
+                NodeOrToken::Node(GreenNode::new(TomlKind::KeyValue.into(), kids))
             }
-            builder.finish_node();
-        }
-        SyntaxElement::Token(t) => builder.token(t.kind().into(), t.text().clone()),
-    }
+            _ => green_element(&el),
+        })
+        .collect::<Vec<_>>();
+    GreenNode::new(TomlKind::InlineTable.into(), kids)
 }
@@ -0,0 +1,93 @@
+use std::cmp::Ordering;
+
+use crate::tkn_tree::path::{split_key, split_raw};
+
+/// How the keys of a sorted table are ordered.
+///
+/// Keys are compared as written, quotes are only ignored when looking a key up
+/// in a priority list or comparing without case. Dotted keys are compared one
+/// segment at a time so `a.b` and `a.c` stay next to each other. Keys that
+/// compare equal keep their order.
+///
+/// A `Matcher` always orders keys `Lexical`, the order of the tables matching
+/// a heading is set with `SortConfigBuilder::key_order`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Plain string comparison, `B` before `a` and `foo10` before `foo2`.
+    #[default]
+    Lexical,
+    /// String comparison ignoring case.
+    CaseInsensitive,
+    /// Runs of digits compare as numbers, `foo2` before `foo10`.
+    Natural,
+    /// The listed keys first in the order given, the rest after them
+    /// alphabetically, as `name`, `version` and `edition` in `[package]`.
+    Priority(Vec<String>),
+}
+
+impl KeyOrder {
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let unquote = |s: &str| s.trim().trim_matches(&['"', '\''][..]).to_string();
+        match self {
+            KeyOrder::Lexical => a.cmp(b),
+            KeyOrder::CaseInsensitive => unquote(a).to_lowercase().cmp(&unquote(b).to_lowercase()),
+            KeyOrder::Natural => natural_cmp(&unquote(a), &unquote(b)),
+            KeyOrder::Priority(first) => {
+                let rank = |s: &str| {
+                    let key = unquote(s);
+                    first.iter().position(|p| *p == key).unwrap_or(first.len())
+                };
+                rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+            }
+        }
+    }
+
+    /// Compares dotted keys one segment at a time so the keys of one dotted
+    /// table stay together, `a.b` and `a.c` are never split by `a-b`. Segments
+    /// that are the same key once unquoted compare equal.
+    pub(crate) fn compare_keys(&self, a: &str, b: &str) -> Ordering {
+        let (raw_a, raw_b) = (split_raw(a), split_raw(b));
+        let (key_a, key_b) = (split_key(a), split_key(b));
+        for i in 0..raw_a.len().min(raw_b.len()) {
+            if key_a[i] == key_b[i] {
+                continue;
+            }
+            let ord = self.compare(raw_a[i].trim(), raw_b[i].trim());
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        raw_a.len().cmp(&raw_b.len())
+    }
+}
+
+/// Compares runs of digits by their value and everything else as text.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (a_digit, b_digit) = (
+            a.starts_with(|c: char| c.is_ascii_digit()),
+            b.starts_with(|c: char| c.is_ascii_digit()),
+        );
+        let run = |s: &str, digits: bool| {
+            s.find(|c: char| c.is_ascii_digit() != digits)
+                .unwrap_or(s.len())
+        };
+        let (a_end, b_end) = (run(a, a_digit), run(b, b_digit));
+        let (a_run, b_run) = (&a[..a_end], &b[..b_end]);
+
+        let ord = match (a_run.is_empty() || b_run.is_empty(), a_digit && b_digit) {
+            (true, _) => return a_run.len().cmp(&b_run.len()),
+            (false, true) => {
+                let (x, y) = (a_run.trim_start_matches('0'), b_run.trim_start_matches('0'));
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (false, false) => a_run.cmp(b_run),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+        a = &a[a_end..];
+        b = &b[b_end..];
+    }
+}
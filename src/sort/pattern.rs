@@ -0,0 +1,109 @@
+use regex::Regex;
+
+use crate::common::err::{ParseTomlError, TomlErrorKind, TomlResult};
+
+/// A single `Matcher` pattern, the prefix of the pattern picks how it matches.
+///
+/// * `exact:` the whole text must be the pattern.
+/// * `prefix:` the text must start with the pattern.
+/// * `glob:` `*` matches any run of characters and `?` a single one.
+/// * `regex:` the pattern is a regular expression, anchor it to match the
+///   whole text.
+///
+/// Patterns without a prefix match when the text contains them.
+pub(crate) enum Pattern<'a> {
+    Contains(&'a str),
+    Exact(&'a str),
+    Prefix(&'a str),
+    Glob(&'a str),
+    /// `None` when the expression is invalid, it matches nothing.
+    Regex(Option<Regex>),
+}
+
+impl<'a> Pattern<'a> {
+    pub(crate) fn new(pat: &'a str) -> Self {
+        if let Some(pat) = pat.strip_prefix("exact:") {
+            Pattern::Exact(pat)
+        } else if let Some(pat) = pat.strip_prefix("prefix:") {
+            Pattern::Prefix(pat)
+        } else if let Some(pat) = pat.strip_prefix("glob:") {
+            Pattern::Glob(pat)
+        } else if let Some(pat) = pat.strip_prefix("regex:") {
+            Pattern::Regex(Regex::new(pat).ok())
+        } else {
+            Pattern::Contains(pat)
+        }
+    }
+
+    /// Compiles each of `pats`.
+    pub(crate) fn all(pats: &[&'a str]) -> Vec<Self> {
+        pats.iter().map(|pat| Pattern::new(pat)).collect()
+    }
+
+    /// Returns an error when `pat` is a `regex:` pattern that does not compile.
+    pub(crate) fn check(pat: &str) -> TomlResult<()> {
+        match pat.strip_prefix("regex:").map(Regex::new) {
+            Some(Err(e)) => Err(ParseTomlError::new(
+                e.to_string(),
+                TomlErrorKind::InternalParseError(pat.into()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Contains(pat) => text.contains(pat),
+            Pattern::Exact(pat) => text == *pat,
+            Pattern::Prefix(pat) => text.starts_with(pat),
+            Pattern::Glob(pat) => glob_match(pat, text),
+            Pattern::Regex(re) => re.as_ref().map(|re| re.is_match(text)) == Some(true),
+        }
+    }
+
+    /// Matches a segmented heading, `heading` is written with its braces.
+    ///
+    /// Patterns without a prefix keep matching the start of a segment as
+    /// `dependencies.` does for `[dependencies.rowan]`, the others are matched
+    /// to the heading without braces.
+    pub(crate) fn is_segment_match(&self, heading: &str) -> bool {
+        let braces: &[char] = &['[', ']'];
+        match self {
+            Pattern::Contains(pat) => heading.contains(&format!("[{}", pat)),
+            _ => self.is_match(heading.trim().trim_matches(braces)),
+        }
+    }
+}
+
+/// Matches `text` against a glob where `*` is any run of characters and `?` is
+/// any single character.
+fn glob_match(pat: &str, text: &str) -> bool {
+    let (pat, text) = (
+        pat.chars().collect::<Vec<_>>(),
+        text.chars().collect::<Vec<_>>(),
+    );
+    let (mut p, mut t) = (0, 0);
+    // where to pick up again when what followed the last `*` failed to match
+    let mut star = None;
+    while t < text.len() {
+        match pat.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pat[p..].iter().all(|c| *c == '*')
+}
@@ -0,0 +1,311 @@
+use std::cmp::Ordering;
+
+use super::array::ItemValue;
+use super::{ArrayOrder, Pattern};
+use crate::edit::{
+    attached_comments, green_element, is_array_table, node, trailing_ws, with_trailing_ws,
+    GreenElement,
+};
+use crate::tkn_tree::{
+    path::{heading_path, split_key},
+    SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, TomlKind,
+};
+
+/// The order of the tables of a Cargo manifest, for `order_tables`.
+pub const CARGO_TABLE_ORDER: &[&str] = &[
+    "exact:[package]",
+    "exact:[lib]",
+    "exact:[[bin]]",
+    "exact:[features]",
+    "exact:[dependencies]",
+    "exact:[dev-dependencies]",
+    "exact:[build-dependencies]",
+    "glob:[target.*]",
+    "exact:[workspace]",
+];
+
+/// A table along with the tables nested below it that directly follow it
+/// and the comments attached above its heading.
+struct Unit {
+    table: SyntaxNode,
+    path: Vec<String>,
+    lead: Vec<SyntaxElement>,
+    /// The children of each table, without the comments attached to the
+    /// heading of the next unit.
+    tables: Vec<Vec<SyntaxElement>>,
+}
+
+impl Unit {
+    fn last(&self) -> &SyntaxElement {
+        self.tables.last().and_then(|t| t.last()).unwrap()
+    }
+}
+
+/// The tables of a document split into units that can be moved.
+struct Units {
+    kids: Vec<SyntaxElement>,
+    /// The end of what comes before the comments attached to the first table.
+    prefix_end: usize,
+    units: Vec<Unit>,
+    /// What follows the last table, it stays at the end.
+    end: Vec<SyntaxElement>,
+    rest: Vec<SyntaxElement>,
+}
+
+impl Units {
+    fn new(root: &SyntaxNode) -> Option<Self> {
+        let kids = root.children_with_tokens().collect::<Vec<_>>();
+        let first = kids.iter().position(|el| el.kind() == TomlKind::Table)?;
+        let prefix_end = attached_comments(&kids, first);
+
+        let mut units = Vec::<Unit>::default();
+        let mut lead = kids[prefix_end..first].to_vec();
+        let mut rest = Vec::default();
+        for el in &kids[first..] {
+            let table = match el {
+                SyntaxElement::Node(n) if n.kind() == TomlKind::Table => n,
+                _ => {
+                    rest.push(el.clone());
+                    continue;
+                }
+            };
+            let mut body = table.children_with_tokens().collect::<Vec<_>>();
+            let start = attached_comments(&body, body.len()).max(1);
+            let tail = body.split_off(start);
+
+            let path = heading_path(table);
+            match units.last_mut() {
+                // the next entry of an array of tables has the same path
+                Some(unit) if path.len() > unit.path.len() && path.starts_with(&unit.path) => {
+                    // nested tables keep the comments above them where they are
+                    unit.tables.last_mut().unwrap().append(&mut lead);
+                    unit.tables.push(body);
+                }
+                _ => units.push(Unit {
+                    table: table.clone(),
+                    path,
+                    lead: std::mem::take(&mut lead),
+                    tables: vec![body],
+                }),
+            }
+            lead = tail;
+        }
+        Some(Units {
+            kids,
+            prefix_end,
+            units,
+            end: lead,
+            rest,
+        })
+    }
+
+    /// Builds the document with the unit `perm[i]` in the place of unit `i`,
+    /// the blank lines between tables stay where they were.
+    fn rebuild(&self, root: &SyntaxNode, perm: &[usize]) -> SyntaxNode {
+        if perm.iter().enumerate().all(|(i, j)| i == *j) {
+            return root.clone();
+        }
+
+        enum Out {
+            Green(GreenElement),
+            Table(Vec<GreenElement>),
+        }
+        let mut out = self.kids[..self.prefix_end]
+            .iter()
+            .map(|el| Out::Green(green_element(el)))
+            .collect::<Vec<_>>();
+        let mut push = |el: GreenElement, is_table: bool| match (out.last_mut(), is_table) {
+            // comments above a heading belong to the table before it
+            (Some(Out::Table(kids)), false) => kids.push(el),
+            (_, false) => out.push(Out::Green(el)),
+            (_, true) => out.push(Out::Table(vec![el])),
+        };
+
+        for (slot, idx) in perm.iter().enumerate() {
+            let unit = &self.units[*idx];
+            let sep = trailing_ws(self.units[slot].last());
+            for el in &unit.lead {
+                push(green_element(el), false);
+            }
+            for (t, table) in unit.tables.iter().enumerate() {
+                let is_last = t + 1 == unit.tables.len();
+                for (i, el) in table.iter().enumerate() {
+                    let green = if is_last && i + 1 == table.len() {
+                        with_trailing_ws(el, &sep)
+                    } else {
+                        green_element(el)
+                    };
+                    // the heading starts a new table
+                    push(green, i == 0);
+                }
+            }
+        }
+        for el in &self.end {
+            push(green_element(el), false);
+        }
+        out.extend(self.rest.iter().map(|el| Out::Green(green_element(el))));
+
+        let kids = out
+            .into_iter()
+            .map(|o| match o {
+                Out::Green(el) => el,
+                Out::Table(kids) => node(TomlKind::Table, kids),
+            })
+            .collect::<Vec<_>>();
+        SyntaxNode::new_root(rowan::GreenNode::new(TomlKind::Root.into(), kids))
+    }
+}
+
+/// The trimmed heading of `table`, braces included.
+fn trimmed_heading(table: &SyntaxNode) -> String {
+    table
+        .first_child()
+        .map(|h| h.token_text().trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Moves whole tables so they follow the order of the patterns in `order`.
+///
+/// Each table is matched by its heading, braces included, to the patterns the
+/// way `Matcher::heading` is. A table takes along the comments attached above
+/// its heading and the tables nested below it that follow it, so `[dependencies]`
+/// moves together with a `[dependencies.rowan]` right after it. Tables that
+/// match no pattern keep their place and tables matching the same pattern keep
+/// their order, the blank lines between tables stay where they were.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{order_tables, parse_it, SyntaxNodeExtTrait, CARGO_TABLE_ORDER};
+///
+/// let toml = "[dependencies]\nrowan = \"0.10\"\n\n# the crate\n[package]\nname = \"a\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let ordered = order_tables(&root, CARGO_TABLE_ORDER);
+/// assert_eq!(
+///     ordered.token_text(),
+///     "# the crate\n[package]\nname = \"a\"\n\n[dependencies]\nrowan = \"0.10\"\n"
+/// );
+/// ```
+pub fn order_tables<S: AsRef<str>>(root: &SyntaxNode, order: &[S]) -> SyntaxNode {
+    let patterns = order
+        .iter()
+        .map(|pat| Pattern::new(pat.as_ref()))
+        .collect::<Vec<_>>();
+    let units = match Units::new(root) {
+        Some(units) => units,
+        None => return root.clone(),
+    };
+    let ranks = units
+        .units
+        .iter()
+        .map(|u| {
+            patterns
+                .iter()
+                .position(|p| p.is_match(&trimmed_heading(&u.table)))
+        })
+        .collect::<Vec<_>>();
+
+    // the tables with a rank are sorted into the places held by ranked tables
+    let mut known = (0..ranks.len())
+        .filter(|i| ranks[*i].is_some())
+        .collect::<Vec<_>>();
+    known.sort_by_key(|i| ranks[*i]);
+    let mut known = known.into_iter();
+    let perm = ranks
+        .iter()
+        .enumerate()
+        .map(|(i, rank)| match rank {
+            Some(_) => known.next().unwrap(),
+            None => i,
+        })
+        .collect::<Vec<_>>();
+    units.rebuild(root, &perm)
+}
+
+/// The value of `key` in the entry `table` of an array of tables, a dotted
+/// `key` is looked up as written in the entry.
+pub(crate) fn entry_value(table: &SyntaxNode, key: &str) -> Option<ItemValue> {
+    let key = split_key(key);
+    table
+        .children()
+        .filter(|n| n.kind() == TomlKind::KeyValue)
+        .find(|kv| {
+            kv.children()
+                .find(|n| n.kind() == TomlKind::Key)
+                .map(|k| split_key(&k.token_text()))
+                == Some(key.clone())
+        })
+        .and_then(|kv| kv.children().find(|n| n.kind() == TomlKind::Value))
+        .map(|value| ItemValue::new(&value))
+}
+
+/// Orders entries of arrays of tables without the value of `key` after
+/// those with one.
+pub(crate) fn entry_cmp(
+    order: &ArrayOrder,
+    a: &Option<ItemValue>,
+    b: &Option<ItemValue>,
+) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => order.compare(a, b),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    }
+}
+
+/// Sorts the entries of each array of tables whose heading matches `heading`
+/// by the value of `key` in each entry.
+///
+/// The heading is matched with its braces, `[[bin]]`, the way
+/// `Matcher::heading` is. Values are compared the way `ArrayOrder` compares
+/// array items and entries without `key` go last. An entry takes along the
+/// comments attached above it and the tables nested below it, `[services.env]`
+/// after a `[[services]]`. Entries are only moved into the places held by
+/// entries of the same array.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, sort_array_tables, SyntaxNodeExtTrait};
+///
+/// let toml = "[[bin]]\nname = \"b\"\n\n[[bin]]\nname = \"a\"\n";
+/// let root = parse_it(toml).unwrap().syntax();
+///
+/// let sorted = sort_array_tables(&root, "[[bin]]", "name");
+/// assert_eq!(
+///     sorted.token_text(),
+///     "[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n"
+/// );
+/// ```
+pub fn sort_array_tables(root: &SyntaxNode, heading: &str, key: &str) -> SyntaxNode {
+    let pattern = Pattern::new(heading);
+    let order = ArrayOrder::default();
+    let units = match Units::new(root) {
+        Some(units) => units,
+        None => return root.clone(),
+    };
+
+    let mut perm = (0..units.units.len()).collect::<Vec<_>>();
+    let mut done = Vec::<&[String]>::default();
+    for unit in &units.units {
+        if !is_array_table(&unit.table)
+            || !pattern.is_match(&trimmed_heading(&unit.table))
+            || done.contains(&unit.path.as_slice())
+        {
+            continue;
+        }
+        done.push(&unit.path);
+
+        let slots = (0..units.units.len())
+            .filter(|i| is_array_table(&units.units[*i].table) && units.units[*i].path == unit.path)
+            .collect::<Vec<_>>();
+        let values = slots
+            .iter()
+            .map(|i| entry_value(&units.units[*i].table, key))
+            .collect::<Vec<_>>();
+        let mut sorted = (0..slots.len()).collect::<Vec<_>>();
+        sorted.sort_by(|a, b| entry_cmp(&order, &values[*a], &values[*b]));
+        for (slot, idx) in slots.iter().zip(sorted) {
+            perm[*slot] = slots[idx];
+        }
+    }
+    units.rebuild(root, &perm)
+}
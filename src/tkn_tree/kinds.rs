@@ -112,7 +112,7 @@ pub enum TomlKind {
     Value,
     /// A toml array.
     Array,
-    ///
+    /// A `Value` inside of an array with optional trailing comma.
     ArrayItem,
     /// Toml date
     /// TODO this is one of with offset, without, local,
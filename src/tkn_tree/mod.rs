@@ -1,12 +1,16 @@
-pub(self) use super::common::{self, err};
+use super::common::{self, err};
 
 mod kinds;
 mod parse_tkns;
+pub(crate) mod path;
 mod syntax;
+mod value;
 pub mod walk;
 
+#[allow(unused_imports)]
 pub use kinds::{TomlKind, TomlNode, TomlToken};
 pub use parse_tkns::Tokenizer;
 pub use syntax::{
     parse_it, ParsedToml, Parser, SyntaxElement, SyntaxNode, SyntaxNodeExtTrait, SyntaxToken,
 };
+pub use value::{TomlDate, ValueExtTrait};
@@ -4,18 +4,17 @@ use rowan::SmolStr;
 use super::err::{ParseTomlError, TomlErrorKind, TomlResult};
 use super::kinds::TomlKind::{self, *};
 
-use chrono::{NaiveDate, NaiveTime};
-
 use super::common::{
-    cmp_tokens, BOOL_END, DATE_CHAR, DATE_END, DATE_LIKE, DATE_TIME, EOL, IDENT_END, INT_END,
-    KEY_END, NUM_END, SEG_END, TIME_CHAR, WHITESPACE,
+    cmp_tokens, BOOL_END, DATE_END, DATE_LIKE, EOL, IDENT_END, INT_END, KEY_END, NUM_END, SEG_END,
+    WHITESPACE,
 };
 use super::kinds::{Element, TomlNode, TomlToken};
 use super::syntax::Parser;
+use super::value::TomlDate;
 
-impl Into<(TomlKind, SmolStr)> for Element {
-    fn into(self) -> (TomlKind, SmolStr) {
-        match self {
+impl From<Element> for (TomlKind, SmolStr) {
+    fn from(el: Element) -> (TomlKind, SmolStr) {
+        match el {
             Element::Node(n) => (n.kind, n.text),
             Element::Token(tkn) => (tkn.kind, tkn.text),
         }
@@ -27,58 +26,6 @@ fn is_valid_key(s: &str) -> bool {
         .all(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.' | '-' | '\'' | '"'))
 }
 
-fn is_valid_datetime(s: &str) -> TomlResult<bool> {
-    let dt = s.split(DATE_TIME).collect::<Vec<_>>();
-    if dt.len() == 1 {
-        if dt[0].contains(':') {
-            let time = dt[0].split(':').collect::<Vec<_>>();
-            if time[2].contains('.') {
-                let (sec, milli) = {
-                    let fractional = time[2].split('.').collect::<Vec<_>>();
-                    (fractional[0].parse()?, fractional[1].parse()?)
-                };
-                NaiveTime::from_hms_milli(time[0].parse()?, time[1].parse()?, sec, milli);
-            } else {
-                NaiveTime::from_hms(time[0].parse()?, time[1].parse()?, time[2].parse()?);
-            };
-            Ok(true)
-        } else {
-            let date = dt[0].split('-').collect::<Vec<_>>();
-
-            assert_eq!(date.len(), 3);
-
-            let _ = NaiveDate::from_ymd(date[0].parse()?, date[1].parse()?, date[2].parse()?);
-            Ok(true)
-        }
-    } else {
-        let date = dt[0].split(DATE_CHAR).collect::<Vec<_>>();
-        let time = dt[1].split(TIME_CHAR).collect::<Vec<_>>();
-        let _ =
-            if time.len() > 3 {
-                if s.contains('+') {
-                    // TODO dont include offset for now
-                    NaiveDate::from_ymd(date[0].parse()?, date[1].parse()?, date[2].parse()?)
-                        .and_hms(time[0].parse()?, time[1].parse()?, time[2].parse()?)
-                } else {
-                    NaiveDate::from_ymd(date[0].parse()?, date[1].parse()?, date[2].parse()?)
-                        .and_hms_milli(
-                            time[0].parse()?,
-                            time[1].parse()?,
-                            time[2].parse()?,
-                            time[3].parse()?,
-                        )
-                }
-            } else {
-                NaiveDate::from_ymd(date[0].parse()?, date[1].parse()?, date[2].parse()?).and_hms(
-                    time[0].parse()?,
-                    time[1].parse()?,
-                    time[2].parse()?,
-                )
-            };
-        Ok(true)
-    }
-}
-
 impl TomlToken {
     // fn whitespace(muncher: &mut Muncher, parser: &mut Parser) -> TomlResult<()> {
     //     let (s, e) = muncher.eat_until_count(|c| !cmp_tokens(c, WHITESPACE));
@@ -201,7 +148,13 @@ impl TomlToken {
     }
 
     fn ident_double_str(muncher: &mut Muncher, parser: &mut Parser) -> TomlResult<()> {
-        let (s, e) = muncher.eat_until_count(|c| c == &'"');
+        // a quote after an odd run of backslashes is escaped
+        let mut escaped = false;
+        let (s, e) = muncher.eat_until_count(|c| {
+            let end = c == &'"' && !escaped;
+            escaped = c == &'\\' && !escaped;
+            end
+        });
         // TODO is this more efficient than eat_until to String??
         let text = SmolStr::new(&muncher.text()[s..e]);
         parser.builder.token(Ident.into(), text);
@@ -276,7 +229,20 @@ impl TomlToken {
         let (s, e) = muncher.eat_until_count(|c| cmp_tokens(c, INT_END));
         let int = &muncher.text()[s..e];
 
-        if int.chars().all(|c| c.is_numeric()) {
+        let (radix, digits) = match int.get(..2) {
+            Some("0x") => (16, &int[2..]),
+            Some("0o") => (8, &int[2..]),
+            Some("0b") => (2, &int[2..]),
+            // only a decimal integer may have a sign
+            _ => (10, int.strip_prefix(&['-', '+'][..]).unwrap_or(int)),
+        };
+        let valid = !digits.is_empty()
+            && !digits.starts_with('_')
+            && !digits.ends_with('_')
+            && !digits.contains("__")
+            && digits.chars().all(|c| c == '_' || c.is_digit(radix));
+
+        if valid {
             let text = SmolStr::new(int);
             parser.builder.token(Integer.into(), text);
             Ok(())
@@ -331,6 +297,35 @@ impl TomlNode {
         Ok(())
     }
 
+    /// Builds `Float` node from `Whitespace` and an `Ident` token of `inf` or
+    /// `nan` with an optional sign.
+    fn special_float(muncher: &mut Muncher, parser: &mut Parser) -> TomlResult<()> {
+        parser.builder.start_node(Float.into());
+
+        if let Some(ws) = TomlToken::maybe_whitespace(muncher) {
+            let (kind, text) = ws.into();
+            parser.builder.token(kind.into(), text)
+        }
+        let (s, e) = muncher.eat_until_count(|c| cmp_tokens(c, NUM_END));
+        let float = &muncher.text()[s..e];
+        if matches!(float.trim_start_matches(&['-', '+'][..]), "inf" | "nan") {
+            parser.builder.token(Ident.into(), SmolStr::new(float));
+            parser.builder.finish_node();
+            Ok(())
+        } else {
+            let (col, ln) = muncher.cursor_position();
+            let msg = "invalid float".into();
+            Err(ParseTomlError::new(
+                msg,
+                TomlErrorKind::UnexpectedToken {
+                    tkn: float.into(),
+                    ln,
+                    col,
+                },
+            ))
+        }
+    }
+
     /// Builds `Date` node from `Whitespace` and `Date` token and if valid adds them as
     /// children.
     fn date_time(muncher: &mut Muncher, parser: &mut Parser) -> TomlResult<()> {
@@ -343,7 +338,7 @@ impl TomlNode {
 
         let text = SmolStr::new(&muncher.text()[s..e]);
 
-        if is_valid_datetime(&text) != Ok(true) {
+        if TomlDate::parse(&text).is_err() {
             let (col, ln) = muncher.cursor_position();
             let msg = "invalid integer".into();
             Err(ParseTomlError::new(
@@ -471,12 +466,16 @@ impl TomlNode {
             Some('t') | Some('f') => TomlToken::boolean(muncher, parser),
             Some('[') => TomlNode::array(muncher, parser),
             Some('{') => TomlNode::inline_table(muncher, parser),
-            Some(digi) if digi.is_numeric() => {
+            Some('i') | Some('n') => TomlNode::special_float(muncher, parser),
+            Some(digi) if digi.is_numeric() || *digi == '-' || *digi == '+' => {
                 muncher.reset_peek();
                 let raw = muncher
                     .peek_until(|c| cmp_tokens(c, NUM_END))
                     .collect::<String>();
-                if raw.contains(DATE_LIKE) {
+                let unsigned = raw.trim_start_matches(&['-', '+'][..]);
+                if unsigned == "inf" || unsigned == "nan" {
+                    TomlNode::special_float(muncher, parser)
+                } else if unsigned.contains(DATE_LIKE) {
                     TomlNode::date_time(muncher, parser)
                 } else if raw.contains('.') {
                     TomlNode::float(muncher, parser)
@@ -577,8 +576,15 @@ impl TomlNode {
     }
 
     /// Builds `ArrayItem` node from `Whitespace` and whatever `Value` node is present
-    /// and adds them as children.
+    /// and adds them as children. Comments on a line of their own are added to the
+    /// `Array`, a comment after an item on the same line belongs to the `ArrayItem`.
     fn array_item(muncher: &mut Muncher, parser: &mut Parser) -> TomlResult<Option<()>> {
+        muncher.reset_peek();
+        while muncher.peek() == Some(&'#') {
+            TomlNode::comment(muncher, parser)?;
+            muncher.reset_peek();
+        }
+        muncher.reset_peek();
         if muncher.peek() == Some(&']') {
             return Ok(None);
         }
@@ -595,10 +601,16 @@ impl TomlNode {
             let (kind, text) = comma.into();
             parser.builder.token(kind.into(), text);
         }
+        let mut same_line = true;
         if let Some(ws) = TomlToken::maybe_whitespace(muncher) {
             let (kind, text) = ws.into();
+            same_line = !text.contains('\n');
             parser.builder.token(kind.into(), text);
         }
+        muncher.reset_peek();
+        if same_line && muncher.peek() == Some(&'#') {
+            TomlNode::comment(muncher, parser)?;
+        }
 
         parser.builder.finish_node();
         Ok(Some(()))
@@ -636,12 +648,16 @@ impl TomlNode {
             Some('t') | Some('f') => TomlToken::boolean(muncher, parser),
             Some('[') => TomlNode::array(muncher, parser),
             Some('{') => TomlNode::inline_table(muncher, parser),
-            Some(digi) if digi.is_numeric() => {
+            Some('i') | Some('n') => TomlNode::special_float(muncher, parser),
+            Some(digi) if digi.is_numeric() || *digi == '-' || *digi == '+' => {
                 muncher.reset_peek();
                 let raw = muncher
                     .peek_until(|c| cmp_tokens(c, NUM_END))
                     .collect::<String>();
-                if raw.contains(DATE_LIKE) {
+                let unsigned = raw.trim_start_matches(&['-', '+'][..]);
+                if unsigned == "inf" || unsigned == "nan" {
+                    TomlNode::special_float(muncher, parser)
+                } else if unsigned.contains(DATE_LIKE) {
                     TomlNode::date_time(muncher, parser)
                 } else if raw.contains('.') {
                     TomlNode::float(muncher, parser)
@@ -872,7 +888,10 @@ impl TomlNode {
 
         TomlNode::heading(muncher, parser)?;
         loop {
-            if muncher.seek(5).map(|s| s.contains('[')) == Some(true) {
+            // the table ends when the next non whitespace char opens a heading
+            muncher.peek_until_count(|c| !cmp_tokens(c, WHITESPACE));
+            if muncher.peek() == Some(&'[') {
+                muncher.reset_peek();
                 break;
             }
             muncher.reset_peek();
@@ -0,0 +1,87 @@
+use super::kinds::TomlKind::{self, *};
+use super::syntax::{SyntaxNode, SyntaxNodeExtTrait};
+use super::value::unquote;
+
+/// Splits a dotted key or heading into its segments as written, dots
+/// inside of quotes do not split.
+pub(crate) fn split_raw(text: &str) -> Vec<String> {
+    let mut segs = Vec::default();
+    let mut seg = String::default();
+    let mut quote = None;
+    for ch in text.chars() {
+        match (ch, quote) {
+            ('.', None) => segs.push(std::mem::take(&mut seg)),
+            ('"', None) | ('\'', None) => {
+                quote = Some(ch);
+                seg.push(ch);
+            }
+            (ch, Some(q)) if ch == q => {
+                quote = None;
+                seg.push(ch);
+            }
+            _ => seg.push(ch),
+        }
+    }
+    segs.push(seg);
+    segs
+}
+
+/// Splits a dotted key or heading into its unquoted segments.
+pub(crate) fn split_key(text: &str) -> Vec<String> {
+    split_raw(text)
+        .iter()
+        .map(|s| unquote(s).unwrap_or_else(|_| s.trim().to_string()))
+        .collect()
+}
+
+/// The segments of a `Table`'s heading, `[a.b]` and `[[a.b]]` are both `["a", "b"]`.
+pub(crate) fn heading_path(table: &SyntaxNode) -> Vec<String> {
+    match table.children().find(|n| n.kind() == Heading) {
+        Some(heading) => {
+            let text = heading.token_text();
+            split_key(text.trim().trim_start_matches('[').trim_end_matches(']'))
+        }
+        None => vec![],
+    }
+}
+
+/// The full key path of a `KeyValue` node, this includes the heading of
+/// the table it belongs to and the keys of any inline tables it is nested in.
+pub(crate) fn key_path(kv: &SyntaxNode) -> Vec<String> {
+    let mut path = match kv
+        .ancestors()
+        .skip(1)
+        .find(|n| matches!(n.kind(), KeyValue | Table | Root))
+    {
+        Some(n) if n.kind() == KeyValue => key_path(&n),
+        Some(n) if n.kind() == Table => heading_path(&n),
+        _ => vec![],
+    };
+    if let Some(key) = kv.children().find(|n| n.kind() == Key) {
+        path.extend(split_key(&key.token_text()));
+    }
+    path
+}
+
+/// Finds the first node of `kind` for which `path_of` returns `path`.
+pub(crate) fn find_by_path<F, S>(
+    root: &SyntaxNode,
+    kind: TomlKind,
+    path: &[S],
+    path_of: F,
+) -> Option<SyntaxNode>
+where
+    F: Fn(&SyntaxNode) -> Vec<String>,
+    S: AsRef<str>,
+{
+    root.descendants().filter(|n| n.kind() == kind).find(|n| {
+        let found = path_of(n);
+        found.len() == path.len() && found.iter().zip(path).all(|(a, b)| a == b.as_ref())
+    })
+}
+
+/// Finds the `KeyValue` node at `path`, for arrays of tables the first
+/// entry holding the key is returned.
+pub(crate) fn find_key_value<S: AsRef<str>>(root: &SyntaxNode, path: &[S]) -> Option<SyntaxNode> {
+    find_by_path(root, KeyValue, path, key_path)
+}
@@ -0,0 +1,322 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use rowan::TextRange;
+
+use super::common::{DATE_CHAR, DATE_TIME};
+use super::err::{ParseTomlError, TomlErrorKind, TomlResult};
+use super::kinds::TomlKind::{self, *};
+use super::syntax::{SyntaxElement, SyntaxNode, SyntaxNodeExtTrait};
+
+/// One of the four toml date-time flavors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TomlDate {
+    /// `1979-05-27T07:32:00Z` or `1979-05-27T07:32:00-07:00`.
+    OffsetDateTime(DateTime<FixedOffset>),
+    /// `1979-05-27T07:32:00`.
+    LocalDateTime(NaiveDateTime),
+    /// `1979-05-27`.
+    LocalDate(NaiveDate),
+    /// `07:32:00`.
+    LocalTime(NaiveTime),
+}
+
+fn date_err(s: &str) -> ParseTomlError {
+    ParseTomlError::new(format!("failed to parse `{}`", s), TomlErrorKind::DateError)
+}
+
+fn parse_date(s: &str) -> TomlResult<NaiveDate> {
+    let date = s.split(DATE_CHAR).collect::<Vec<_>>();
+    if date.len() != 3 {
+        return Err(date_err(s));
+    }
+    NaiveDate::from_ymd_opt(date[0].parse()?, date[1].parse()?, date[2].parse()?)
+        .ok_or_else(|| date_err(s))
+}
+
+fn parse_time(s: &str) -> TomlResult<NaiveTime> {
+    let time = s.split(':').collect::<Vec<_>>();
+    if time.len() != 3 {
+        return Err(date_err(s));
+    }
+    let (sec, nano) = match time[2].split_once('.') {
+        Some((sec, frac)) => {
+            // only nanosecond precision is kept, anything past that is truncated
+            let digits = frac.chars().take(9).collect::<String>();
+            let nano = digits.parse::<u32>()? * 10_u32.pow(9 - digits.len() as u32);
+            (sec.parse()?, nano)
+        }
+        None => (time[2].parse()?, 0),
+    };
+    NaiveTime::from_hms_nano_opt(time[0].parse()?, time[1].parse()?, sec, nano)
+        .ok_or_else(|| date_err(s))
+}
+
+fn parse_offset(s: &str) -> TomlResult<FixedOffset> {
+    if s == "Z" || s == "z" {
+        return FixedOffset::east_opt(0).ok_or_else(|| date_err(s));
+    }
+    let sign = if s.starts_with('-') { -1 } else { 1 };
+    let (hr, min) = s[1..].split_once(':').ok_or_else(|| date_err(s))?;
+    let secs = sign * (hr.parse::<i32>()? * 3600 + min.parse::<i32>()? * 60);
+    FixedOffset::east_opt(secs).ok_or_else(|| date_err(s))
+}
+
+impl TomlDate {
+    /// Parses the text of a `Date` node, this is also what the tokenizer uses
+    /// to decide if a date is valid.
+    pub(crate) fn parse(s: &str) -> TomlResult<TomlDate> {
+        let (date, time) = match s.find(DATE_TIME) {
+            Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+            None if s.contains(':') => return parse_time(s).map(TomlDate::LocalTime),
+            None => (s, None),
+        };
+        let date = parse_date(date)?;
+        let time = match time {
+            Some(time) => time,
+            None => return Ok(TomlDate::LocalDate(date)),
+        };
+
+        match time.find(&['Z', 'z', '+', '-'][..]) {
+            Some(idx) => {
+                let offset = parse_offset(&time[idx..])?;
+                let local = date.and_time(parse_time(&time[..idx])?);
+                offset
+                    .from_local_datetime(&local)
+                    .single()
+                    .map(TomlDate::OffsetDateTime)
+                    .ok_or_else(|| date_err(s))
+            }
+            None => Ok(TomlDate::LocalDateTime(date.and_time(parse_time(time)?))),
+        }
+    }
+}
+
+fn mismatch(expected: TomlKind, found: TomlKind, range: TextRange) -> ParseTomlError {
+    ParseTomlError::new(
+        format!("expected {:?}", expected),
+        TomlErrorKind::TypeMismatch {
+            expected,
+            found,
+            range,
+        },
+    )
+}
+
+fn str_err(msg: String) -> ParseTomlError {
+    ParseTomlError::new(msg, TomlErrorKind::StringError)
+}
+
+/// Returns the first non whitespace child of a `Value` node, this is the
+/// actual node or token holding the value.
+fn value_element(node: &SyntaxNode) -> TomlResult<SyntaxElement> {
+    if node.kind() != Value {
+        return Err(mismatch(Value, node.kind(), node.text_range()));
+    }
+    node.children_with_tokens()
+        .find(|el| el.kind() != Whitespace)
+        .ok_or_else(|| mismatch(Value, Whitespace, node.text_range()))
+}
+
+fn expect_kind(node: &SyntaxNode, expected: TomlKind) -> TomlResult<SyntaxElement> {
+    let el = value_element(node)?;
+    if el.kind() == expected {
+        Ok(el)
+    } else {
+        Err(mismatch(expected, el.kind(), el.text_range()))
+    }
+}
+
+fn element_text(el: &SyntaxElement) -> String {
+    match el {
+        SyntaxElement::Node(n) => n.token_text(),
+        SyntaxElement::Token(t) => t.text().to_string(),
+    }
+}
+
+fn parse_integer(raw: &str) -> TomlResult<i64> {
+    let cleaned = raw.replace('_', "");
+    let (neg, digits) = match cleaned.as_bytes().first() {
+        Some(b'-') => (true, &cleaned[1..]),
+        Some(b'+') => (false, &cleaned[1..]),
+        _ => (false, cleaned.as_str()),
+    };
+    let int = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)?
+    } else if let Some(oct) = digits.strip_prefix("0o") {
+        i64::from_str_radix(oct, 8)?
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2)?
+    } else {
+        // parsed with its sign so `i64::MIN` fits
+        return Ok(cleaned.parse()?);
+    };
+    Ok(if neg { -int } else { int })
+}
+
+fn unescape(raw: &str) -> TomlResult<String> {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push('\u{8}'),
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('f') => out.push('\u{c}'),
+            Some('r') => out.push('\r'),
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some(u @ 'u') | Some(u @ 'U') => {
+                let len = if u == 'u' { 4 } else { 8 };
+                let hex = chars.by_ref().take(len).collect::<String>();
+                let ch = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or_else(|| str_err(format!("invalid unicode escape `\\{}{}`", u, hex)))?;
+                out.push(ch);
+            }
+            // line ending backslash, trims all whitespace up to the next non whitespace char
+            Some(ws) if ws.is_whitespace() => {
+                while chars.peek().map(|c| c.is_whitespace()) == Some(true) {
+                    chars.next();
+                }
+            }
+            Some(esc) => return Err(str_err(format!("invalid escape `\\{}`", esc))),
+            None => return Err(str_err("string ends with `\\`".into())),
+        }
+    }
+    Ok(out)
+}
+
+/// Decodes a `Str` node, used for both string values and quoted keys.
+pub(crate) fn decode_str(node: &SyntaxNode) -> TomlResult<String> {
+    let mut tkns = node
+        .children_with_tokens()
+        .filter_map(|el| el.into_token())
+        .filter(|t| t.kind() != Whitespace);
+    let quote = tkns
+        .next()
+        .ok_or_else(|| mismatch(Str, node.kind(), node.text_range()))?;
+    let raw = tkns
+        .filter(|t| t.kind() != quote.kind())
+        .map(|t| t.text().to_string())
+        .collect::<String>();
+
+    match quote.kind() {
+        SingleQuote => Ok(raw),
+        DoubleQuote => unescape(&raw),
+        TripleQuote => {
+            // a newline immediately following the opening quotes is trimmed
+            let raw = raw
+                .strip_prefix("\r\n")
+                .or_else(|| raw.strip_prefix('\n'))
+                .unwrap_or(&raw);
+            unescape(raw)
+        }
+        kind => Err(mismatch(Str, kind, quote.text_range())),
+    }
+}
+
+/// Returns the text of a `Key` node with quotes removed and escapes decoded.
+pub(crate) fn decode_key(node: &SyntaxNode) -> TomlResult<String> {
+    if let Some(s) = node.first_child().filter(|n| n.kind() == Str) {
+        return decode_str(&s);
+    }
+    // the tokenizer may leave a quoted key as a single `Ident`
+    unquote(&node.token_text())
+}
+
+/// Removes the quotes from a single key or heading segment.
+pub(crate) fn unquote(text: &str) -> TomlResult<String> {
+    let text = text.trim();
+    if text.len() > 1 && text.starts_with('"') && text.ends_with('"') {
+        unescape(&text[1..text.len() - 1])
+    } else if text.len() > 1 && text.starts_with('\'') && text.ends_with('\'') {
+        Ok(text[1..text.len() - 1].to_string())
+    } else {
+        Ok(text.to_string())
+    }
+}
+
+/// Typed accessors for `TomlKind::Value` nodes.
+///
+/// Each accessor returns a `TomlErrorKind::TypeMismatch` error holding the
+/// range of the offending node when the value is not of the requested type.
+pub trait ValueExtTrait {
+    /// The value of a `Bool` token.
+    fn as_bool(&self) -> TomlResult<bool>;
+    /// The value of an `Integer` token, underscores and `0x`, `0o`
+    /// and `0b` prefixes are understood.
+    fn as_i64(&self) -> TomlResult<i64>;
+    /// The value of a `Float` node.
+    fn as_f64(&self) -> TomlResult<f64>;
+    /// The decoded contents of a `Str` node, quotes are removed and
+    /// escapes are processed for basic strings.
+    fn as_str(&self) -> TomlResult<String>;
+    /// The value of a `Date` node.
+    fn as_datetime(&self) -> TomlResult<TomlDate>;
+    /// The `Value` node of each `ArrayItem`.
+    fn as_array(&self) -> TomlResult<Vec<SyntaxNode>>;
+    /// The decoded key and `Value` node of each `KeyValue`.
+    fn as_inline_table(&self) -> TomlResult<Vec<(String, SyntaxNode)>>;
+}
+
+impl ValueExtTrait for SyntaxNode {
+    fn as_bool(&self) -> TomlResult<bool> {
+        let el = expect_kind(self, Bool)?;
+        Ok(element_text(&el) == "true")
+    }
+
+    fn as_i64(&self) -> TomlResult<i64> {
+        let el = expect_kind(self, Integer)?;
+        parse_integer(&element_text(&el))
+    }
+
+    fn as_f64(&self) -> TomlResult<f64> {
+        let el = expect_kind(self, Float)?;
+        Ok(element_text(&el).trim().replace('_', "").parse()?)
+    }
+
+    fn as_str(&self) -> TomlResult<String> {
+        let el = expect_kind(self, Str)?;
+        decode_str(el.as_node().unwrap())
+    }
+
+    fn as_datetime(&self) -> TomlResult<TomlDate> {
+        let el = expect_kind(self, Date)?;
+        TomlDate::parse(element_text(&el).trim())
+    }
+
+    fn as_array(&self) -> TomlResult<Vec<SyntaxNode>> {
+        let el = expect_kind(self, Array)?;
+        Ok(el
+            .as_node()
+            .unwrap()
+            .children()
+            .filter(|n| n.kind() == ArrayItem)
+            .filter_map(|n| n.children().find(|n| n.kind() == Value))
+            .collect())
+    }
+
+    fn as_inline_table(&self) -> TomlResult<Vec<(String, SyntaxNode)>> {
+        let el = expect_kind(self, InlineTable)?;
+        el.as_node()
+            .unwrap()
+            .children()
+            .filter(|n| n.kind() == KeyValue)
+            .map(|kv| {
+                let key = kv
+                    .children()
+                    .find(|n| n.kind() == Key)
+                    .ok_or_else(|| mismatch(Key, kv.kind(), kv.text_range()))?;
+                let val = kv
+                    .children()
+                    .find(|n| n.kind() == Value)
+                    .ok_or_else(|| mismatch(Value, kv.kind(), kv.text_range()))?;
+                Ok((decode_key(&key)?, val))
+            })
+            .collect()
+    }
+}
@@ -1,28 +1,39 @@
 use std::cell::Cell;
-use std::fmt;
 
 use rowan::Direction;
 
 use super::tkn_tree::{SyntaxElement, SyntaxToken, TomlKind};
 use super::ws::WhiteSpace;
 
+/// A comma the formatter adds or drops, the only token it ever changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CommaEdit {
+    Keep,
+    /// Write a comma before the whitespace of this block.
+    Insert,
+    /// Leave out this block, a trailing comma.
+    Remove,
+}
+
 #[derive(Debug, Clone)]
 pub struct Block {
     tkn: SyntaxToken,
     pub(crate) whitespace: Cell<WhiteSpace>,
-}
-
-impl fmt::Display for Block {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.whitespace.get().space_before)?;
-        write!(f, "{}", self.tkn.text())
-    }
+    pub(crate) comma: Cell<CommaEdit>,
+    indent_unit: u32,
 }
 
 impl Block {
-    pub fn new(tkn: SyntaxToken) -> Block {
-        let whitespace = Cell::new(WhiteSpace::new(&tkn));
-        Self { tkn, whitespace }
+    /// A block of `tkn`, the indents around it are measured in levels of
+    /// `indent_unit` spaces.
+    pub fn new(tkn: SyntaxToken, indent_unit: u32) -> Block {
+        let whitespace = Cell::new(WhiteSpace::new(&tkn, indent_unit));
+        Self {
+            tkn,
+            whitespace,
+            comma: Cell::new(CommaEdit::Keep),
+            indent_unit,
+        }
     }
 
     pub fn kind(&self) -> TomlKind {
@@ -37,6 +48,11 @@ impl Block {
         self.whitespace.get()
     }
 
+    /// The number of spaces one indent level of the source is made of.
+    pub fn indent_unit(&self) -> u32 {
+        self.indent_unit
+    }
+
     pub fn parents_contain(&self, pat: &str) -> bool {
         let newline = |node: SyntaxElement| -> bool {
             match node {
@@ -1,36 +1,56 @@
 use std::fmt;
 
-pub(self) use super::tkn_tree::{self, walk::walk_tokens_non_ws, SyntaxNode, TomlKind};
+use super::edit::TextEdit;
+use super::tkn_tree::{self, walk::walk_tokens_non_ws, SyntaxNode, TomlKind};
 
 mod block;
+mod options;
 mod rules;
+mod width;
 mod ws;
 
-use block::Block;
+use block::{Block, CommaEdit};
+pub use options::{FormatOptions, IndentStyle, NewlineStyle};
 use rules::{
     indent_after_comma, indent_after_open_brace, lf_after_heading, lf_after_table, none_around_dot,
     space_around_eq, space_lf_after_array_open, space_lf_after_comma,
     space_lf_after_inline_table_open, space_lf_before_array_close,
-    space_lf_before_inline_table_close,
+    space_lf_before_inline_table_close, wrap_array,
 };
 use ws::WhiteSpace;
 
-type RuleFn = Box<dyn for<'a> Fn(&'a Block, &'a Block) -> Option<WhiteSpace>>;
+type RuleFn = Box<dyn for<'a> Fn(&'a Block, &'a Block, &'a FormatOptions) -> Option<WhiteSpace>>;
 
 /// Formatter impl's `Display` so once `format()` has been called, the resulting
 /// text can be retrieved.
 pub struct Formatter {
     blocks: Vec<Block>,
     rules: Vec<(TomlKind, RuleFn)>,
+    options: FormatOptions,
     formatted: String,
 }
 
 impl Formatter {
     /// Creates new instance of `Formatter` for formatting a tree of `rowan::SyntaxNode`s.
     pub fn new(root: &SyntaxNode) -> Formatter {
+        Self::with_options(root, FormatOptions::default())
+    }
+    /// Creates a `Formatter` that lays out whitespace according to `options`.
+    pub fn with_options(root: &SyntaxNode, options: FormatOptions) -> Formatter {
+        let options = options.resolve(root);
+        // indents are read in the unit the document uses and written in
+        // `indent_width`, preserved indents are read in `indent_width` so
+        // they come out as they went in
+        let unit = match options.indent_style {
+            IndentStyle::Preserve => options.indent_width,
+            _ => ws::indent_unit(root).unwrap_or(options.indent_width),
+        };
         Self {
-            blocks: walk_tokens_non_ws(root).map(Block::new).collect(),
+            blocks: walk_tokens_non_ws(root)
+                .map(|tkn| Block::new(tkn, unit))
+                .collect(),
             rules: formatter(),
+            options,
             formatted: String::default(),
         }
     }
@@ -47,23 +67,62 @@ impl Formatter {
                 .map(|(_, func)| func);
 
             for rule in rules {
-                if let Some(fixed) = rule(l_blk, r_blk) {
+                if let Some(fixed) = rule(l_blk, r_blk, &self.options) {
                     r_blk.whitespace.set(fixed);
                 }
             }
         }
-        self.formatted = self
-            .blocks
-            .clone()
-            .into_iter()
-            .map(|b| b.to_string())
-            .collect();
+        self.formatted = self.blocks.iter().map(|b| self.render(b)).collect();
 
-        if !self.formatted.ends_with('\n') {
-            self.formatted.push('\n')
+        if self.options.trailing_newline && !self.formatted.ends_with('\n') {
+            self.formatted.push_str(self.options.line_ending())
         }
         self
     }
+    /// The text written for `blk` and the whitespace before it, the line ending
+    /// before the end of the file is dropped without `trailing_newline`.
+    fn render(&self, blk: &Block) -> String {
+        let mut text = match blk.comma.get() {
+            CommaEdit::Insert => String::from(","),
+            _ => String::default(),
+        };
+        if blk.kind() != TomlKind::EoF || self.options.trailing_newline {
+            text.push_str(&blk.whitespace().space_before.render(&self.options));
+        }
+        if blk.comma.get() != CommaEdit::Remove {
+            text.push_str(blk.token().text());
+        }
+        text
+    }
+    /// The edits that turn the original text into the formatted text, only
+    /// the whitespace between tokens and the trailing commas of arrays are
+    /// ever changed. Call after `format()`.
+    pub fn text_edits(&self) -> Vec<TextEdit> {
+        let root = match self.blocks.first() {
+            Some(blk) => blk.token().parent().ancestors().last().unwrap(),
+            None => return vec![],
+        };
+        let text = root.text().to_string();
+
+        let mut edits = Vec::default();
+        let mut prev_end = 0;
+        for blk in &self.blocks {
+            let end = usize::from(blk.token().text_range().end());
+            edits.extend(TextEdit::minimal(
+                &text[prev_end..end],
+                prev_end,
+                &self.render(blk),
+            ));
+            prev_end = end;
+        }
+        let end = if self.formatted.ends_with('\n') && !text[..prev_end].ends_with('\n') {
+            self.options.line_ending()
+        } else {
+            ""
+        };
+        edits.extend(TextEdit::minimal(&text[prev_end..], prev_end, end));
+        edits
+    }
 }
 impl fmt::Debug for Formatter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -73,6 +132,7 @@ impl fmt::Debug for Formatter {
                 "rules",
                 &self.rules.iter().map(|(k, _fn)| k).collect::<Vec<_>>(),
             )
+            .field("options", &self.options)
             .field("formatted", &self.formatted)
             .finish()
     }
@@ -125,5 +185,11 @@ pub fn formatter() -> Vec<(TomlKind, RuleFn)> {
         ),
         // indent after comma if siblings are indented
         (TomlKind::Comma, Box::new(indent_after_comma) as RuleFn),
+        // WIDTH
+        // explode or collapse arrays to fit the max width, these go last so
+        // they win over the rules above
+        (TomlKind::OpenBrace, Box::new(wrap_array) as RuleFn),
+        (TomlKind::Comma, Box::new(wrap_array) as RuleFn),
+        (TomlKind::CloseBrace, Box::new(wrap_array) as RuleFn),
     ]
 }
@@ -0,0 +1,100 @@
+use super::tkn_tree::SyntaxNode;
+use crate::edit::line_ending;
+
+/// The line ending `Formatter` writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NewlineStyle {
+    /// `\n`.
+    Unix,
+    /// `\r\n`.
+    Windows,
+    /// `\r\n` when the document uses it anywhere, `\n` otherwise.
+    Auto,
+}
+
+/// How indentation is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// Tabs where the indent being copied used tabs, spaces elsewhere, space
+    /// indents keep their width.
+    Preserve,
+    /// `indent_width` spaces for each level, the levels of the document are
+    /// counted in the indent it uses.
+    Spaces,
+    /// One tab for each level, counted like `Spaces`.
+    Tabs,
+}
+
+/// The layout `Formatter` applies, the default keeps what the formatter has
+/// always done.
+///
+/// # Examples
+/// ```
+/// use toml_parse::{parse_it, FormatOptions, Formatter};
+///
+/// let options = FormatOptions {
+///     space_inside_braces: false,
+///     ..FormatOptions::default()
+/// };
+/// let parsed = parse_it("key = [ 1,2 ]").unwrap().syntax();
+/// let fmted = Formatter::with_options(&parsed, options).format();
+/// assert_eq!(fmted.to_string(), "key = [1, 2]\n");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The number of columns of one level of indentation, a tab counts as
+    /// one level.
+    pub indent_width: u32,
+    pub indent_style: IndentStyle,
+    pub newline: NewlineStyle,
+    /// End the document with a line ending.
+    pub trailing_newline: bool,
+    /// The number of blank lines before each table after the first.
+    pub blank_lines_between_tables: u32,
+    /// A space after `[` and `{` and before `]` and `}` when the array or
+    /// inline table is on one line, `[ 1, 2 ]` rather than `[1, 2]`.
+    pub space_inside_braces: bool,
+    /// The widest a line with an array may be, an array that does not fit is
+    /// written with one item per line and a trailing comma.
+    pub max_width: Option<u32>,
+    /// Put multi-line arrays that fit within `max_width` back on one line,
+    /// arrays holding comments are left as they are.
+    pub collapse_arrays: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            indent_style: IndentStyle::Preserve,
+            newline: NewlineStyle::Unix,
+            trailing_newline: true,
+            blank_lines_between_tables: 1,
+            space_inside_braces: true,
+            max_width: None,
+            collapse_arrays: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Picks the line ending of `root` for `NewlineStyle::Auto`.
+    pub(crate) fn resolve(mut self, root: &SyntaxNode) -> Self {
+        if self.newline == NewlineStyle::Auto {
+            self.newline = match line_ending(root) {
+                "\r\n" => NewlineStyle::Windows,
+                _ => NewlineStyle::Unix,
+            };
+        }
+        // a zero width would make every indent level empty
+        self.indent_width = self.indent_width.max(1);
+        self
+    }
+
+    pub(crate) fn line_ending(&self) -> &'static str {
+        match self.newline {
+            NewlineStyle::Windows => "\r\n",
+            _ => "\n",
+        }
+    }
+}
@@ -1,21 +1,23 @@
 use rowan::Direction;
 
-use super::block::Block;
-use super::tkn_tree::TomlKind;
+use super::block::{Block, CommaEdit};
+use super::tkn_tree::{SyntaxNode, TomlKind};
+use super::width::{array_layout, Layout};
 use super::ws::{calc_indent, Space, SpaceLoc, SpaceValue, WhiteSpace};
+use super::FormatOptions;
 
 const LF_BEFORE: Space = Space {
     value: SpaceValue::Newline,
     loc: SpaceLoc::Before,
 };
 
-const MULTI_LF_BEFORE: Space = Space {
-    value: SpaceValue::MultiLF(2),
+const MAYBE_LF_BEFORE: Space = Space {
+    value: SpaceValue::SingleOptionalNewline,
     loc: SpaceLoc::Before,
 };
 
-const MAYBE_LF_BEFORE: Space = Space {
-    value: SpaceValue::SingleOptionalNewline,
+const MAYBE_LF_NONE_BEFORE: Space = Space {
+    value: SpaceValue::NoneOptionalNewline,
     loc: SpaceLoc::Before,
 };
 
@@ -29,7 +31,20 @@ const NONE: Space = Space {
     loc: SpaceLoc::After,
 };
 
-pub(crate) fn lf_after_heading(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+/// The space after an open brace and before a close brace on the same line.
+fn inside_braces(options: &FormatOptions) -> Space {
+    if options.space_inside_braces {
+        MAYBE_LF_BEFORE
+    } else {
+        MAYBE_LF_NONE_BEFORE
+    }
+}
+
+pub(crate) fn lf_after_heading(
+    l_blk: &Block,
+    r_blk: &Block,
+    _: &FormatOptions,
+) -> Option<WhiteSpace> {
     if l_blk
         .token()
         .ancestors()
@@ -43,7 +58,11 @@ pub(crate) fn lf_after_heading(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpac
     None
 }
 
-pub(crate) fn lf_after_table(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+pub(crate) fn lf_after_table(
+    l_blk: &Block,
+    r_blk: &Block,
+    options: &FormatOptions,
+) -> Option<WhiteSpace> {
     let not_first_table = if let Some(table) = r_blk
         .token()
         .ancestors()
@@ -54,6 +73,10 @@ pub(crate) fn lf_after_table(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace>
         false
     };
     let not_comment = l_blk.kind() != TomlKind::CommentText;
+    let blank_lines = Space {
+        value: SpaceValue::MultiLF(options.blank_lines_between_tables + 1),
+        loc: SpaceLoc::Before,
+    };
     let once_for_array_table = if r_blk.token().parent().kind() == TomlKind::ArrayHeading {
         r_blk.token().next_sibling_or_token().unwrap().kind() == TomlKind::OpenBrace
     } else {
@@ -64,18 +87,22 @@ pub(crate) fn lf_after_table(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace>
         .token()
         .ancestors()
         .any(|n| n.kind() == TomlKind::Heading)
-        && !r_blk.whitespace().match_space_before(MULTI_LF_BEFORE)
+        && !r_blk.whitespace().match_space_before(blank_lines)
         && r_blk.kind() == TomlKind::OpenBrace
         && not_first_table
         && not_comment
         && once_for_array_table
     {
-        return Some(WhiteSpace::from_rule(&MULTI_LF_BEFORE, l_blk, r_blk));
+        return Some(WhiteSpace::from_rule(&blank_lines, l_blk, r_blk));
     }
     None
 }
 
-pub(crate) fn space_lf_after_comma(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+pub(crate) fn space_lf_after_comma(
+    l_blk: &Block,
+    r_blk: &Block,
+    _: &FormatOptions,
+) -> Option<WhiteSpace> {
     if !r_blk.whitespace().match_space_before(MAYBE_LF_BEFORE) && l_blk.kind() == TomlKind::Comma {
         // println!("MATCH {:#?} {:#?}", l_blk, r_blk);
         return Some(WhiteSpace::from_rule(&MAYBE_LF_BEFORE, l_blk, r_blk));
@@ -83,12 +110,18 @@ pub(crate) fn space_lf_after_comma(l_blk: &Block, r_blk: &Block) -> Option<White
     None
 }
 
-pub(crate) fn space_lf_after_inline_table_open(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
-    if !r_blk.whitespace().match_space_before(MAYBE_LF_BEFORE)
+pub(crate) fn space_lf_after_inline_table_open(
+    l_blk: &Block,
+    r_blk: &Block,
+    options: &FormatOptions,
+) -> Option<WhiteSpace> {
+    if !r_blk
+        .whitespace()
+        .match_space_before(inside_braces(options))
         && l_blk.kind() == TomlKind::OpenCurly
     {
         // println!("MATCH {:#?} {:#?}", l_blk, r_blk);
-        return Some(WhiteSpace::from_rule(&MAYBE_LF_BEFORE, l_blk, r_blk));
+        return Some(WhiteSpace::from_rule(&inside_braces(options), l_blk, r_blk));
     }
     None
 }
@@ -96,45 +129,64 @@ pub(crate) fn space_lf_after_inline_table_open(l_blk: &Block, r_blk: &Block) ->
 pub(crate) fn space_lf_before_inline_table_close(
     l_blk: &Block,
     r_blk: &Block,
+    options: &FormatOptions,
 ) -> Option<WhiteSpace> {
-    if !r_blk.whitespace().match_space_before(MAYBE_LF_BEFORE)
+    if !r_blk
+        .whitespace()
+        .match_space_before(inside_braces(options))
         && r_blk.kind() == TomlKind::CloseCurly
     {
         // println!("MATCH {:#?} {:#?}", l_blk, r_blk);
-        return Some(WhiteSpace::from_rule(&MAYBE_LF_BEFORE, l_blk, r_blk));
+        return Some(WhiteSpace::from_rule(&inside_braces(options), l_blk, r_blk));
     }
     None
 }
 
-pub(crate) fn space_lf_after_array_open(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+pub(crate) fn space_lf_after_array_open(
+    l_blk: &Block,
+    r_blk: &Block,
+    options: &FormatOptions,
+) -> Option<WhiteSpace> {
     if l_blk
         .token()
         .ancestors()
         .any(|n| n.kind() == TomlKind::Value)
-        && !r_blk.whitespace().match_space_before(MAYBE_LF_BEFORE)
+        && !r_blk
+            .whitespace()
+            .match_space_before(inside_braces(options))
         && l_blk.kind() == TomlKind::OpenBrace
     {
         // println!("MATCH {:#?} {:#?}", l_blk, r_blk);
-        return Some(WhiteSpace::from_rule(&MAYBE_LF_BEFORE, l_blk, r_blk));
+        return Some(WhiteSpace::from_rule(&inside_braces(options), l_blk, r_blk));
     }
     None
 }
 
-pub(crate) fn space_lf_before_array_close(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+pub(crate) fn space_lf_before_array_close(
+    l_blk: &Block,
+    r_blk: &Block,
+    options: &FormatOptions,
+) -> Option<WhiteSpace> {
     if r_blk
         .token()
         .ancestors()
         .any(|n| n.kind() == TomlKind::Value)
-        && !r_blk.whitespace().match_space_before(MAYBE_LF_BEFORE)
+        && !r_blk
+            .whitespace()
+            .match_space_before(inside_braces(options))
         && r_blk.kind() == TomlKind::CloseBrace
     {
         // println!("MATCH {:#?} {:#?}", l_blk, r_blk);
-        return Some(WhiteSpace::from_rule(&MAYBE_LF_BEFORE, l_blk, r_blk));
+        return Some(WhiteSpace::from_rule(&inside_braces(options), l_blk, r_blk));
     }
     None
 }
 
-pub(crate) fn space_around_eq(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+pub(crate) fn space_around_eq(
+    l_blk: &Block,
+    r_blk: &Block,
+    _: &FormatOptions,
+) -> Option<WhiteSpace> {
     if r_blk
         .token()
         .ancestors()
@@ -147,7 +199,11 @@ pub(crate) fn space_around_eq(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace
     None
 }
 
-pub(crate) fn none_around_dot(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+pub(crate) fn none_around_dot(
+    l_blk: &Block,
+    r_blk: &Block,
+    _: &FormatOptions,
+) -> Option<WhiteSpace> {
     if r_blk
         .token()
         .ancestors()
@@ -161,7 +217,11 @@ pub(crate) fn none_around_dot(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace
 }
 
 #[allow(clippy::collapsible_if)]
-pub(crate) fn indent_after_comma(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+pub(crate) fn indent_after_comma(
+    l_blk: &Block,
+    r_blk: &Block,
+    _: &FormatOptions,
+) -> Option<WhiteSpace> {
     let (has_indent, is_tab, (level, alignment)) = if let Some(arr_item) = r_blk
         .token()
         .ancestors()
@@ -180,7 +240,11 @@ pub(crate) fn indent_after_comma(l_blk: &Block, r_blk: &Block) -> Option<WhiteSp
             }) == Some(true)
         }) {
             let raw_ws = ws.as_token().unwrap().text();
-            (true, raw_ws.contains('\t'), calc_indent(raw_ws))
+            (
+                true,
+                raw_ws.contains('\t'),
+                calc_indent(raw_ws, r_blk.indent_unit()),
+            )
         } else if let Some(ws) = arr_item.siblings_with_tokens(Direction::Next).find(|el| {
             // println!("EL NEXT {:#?}", el);
             el.as_token().map(|t| {
@@ -194,7 +258,11 @@ pub(crate) fn indent_after_comma(l_blk: &Block, r_blk: &Block) -> Option<WhiteSp
             }) == Some(true)
         }) {
             let raw_ws = ws.as_token().unwrap().text();
-            (true, raw_ws.contains('\t'), calc_indent(raw_ws))
+            (
+                true,
+                raw_ws.contains('\t'),
+                calc_indent(raw_ws, r_blk.indent_unit()),
+            )
         } else {
             (false, false, (0, 0))
         }
@@ -229,7 +297,9 @@ pub(crate) fn indent_after_comma(l_blk: &Block, r_blk: &Block) -> Option<WhiteSp
         //     r_blk,
         //     r_blk.whitespace().space_before == indent
         // );
+        // a comment after the comma stays on the line of its item
         if l_blk.kind() == TomlKind::Comma
+            && r_blk.kind() != TomlKind::Hash
             && has_indent
             && r_blk.whitespace().space_before != indent
         {
@@ -240,7 +310,11 @@ pub(crate) fn indent_after_comma(l_blk: &Block, r_blk: &Block) -> Option<WhiteSp
 }
 
 #[allow(clippy::collapsible_if)]
-pub(crate) fn indent_after_open_brace(l_blk: &Block, r_blk: &Block) -> Option<WhiteSpace> {
+pub(crate) fn indent_after_open_brace(
+    l_blk: &Block,
+    r_blk: &Block,
+    _: &FormatOptions,
+) -> Option<WhiteSpace> {
     let (has_indent, is_tab, (level, alignment)) = if let Some(arr_item) =
         l_blk.token().next_sibling_or_token().and_then(|t| {
             if t.kind() == TomlKind::ArrayItem {
@@ -272,7 +346,11 @@ pub(crate) fn indent_after_open_brace(l_blk: &Block, r_blk: &Block) -> Option<Wh
             }) == Some(true)
         }) {
             let raw_ws = ws.as_token().unwrap().text();
-            (true, raw_ws.contains('\t'), calc_indent(raw_ws))
+            (
+                true,
+                raw_ws.contains('\t'),
+                calc_indent(raw_ws, l_blk.indent_unit()),
+            )
         } else {
             (false, false, (0, 0))
         }
@@ -296,3 +374,61 @@ pub(crate) fn indent_after_open_brace(l_blk: &Block, r_blk: &Block) -> Option<Wh
     }
     None
 }
+
+/// The array whose items `l_blk` and `r_blk` are between, along with whether
+/// `l_blk` is a comma of it.
+fn array_between(l_blk: &Block, r_blk: &Block) -> Option<(SyntaxNode, bool)> {
+    let l_parent = l_blk.token().parent();
+    let r_parent = r_blk.token().parent();
+    match (l_blk.kind(), r_blk.kind()) {
+        (TomlKind::OpenBrace, _) if l_parent.kind() == TomlKind::Array => Some((l_parent, false)),
+        (TomlKind::Comma, _) if l_parent.kind() == TomlKind::ArrayItem => {
+            Some((l_parent.parent()?, true))
+        }
+        (_, TomlKind::CloseBrace) if r_parent.kind() == TomlKind::Array => Some((r_parent, false)),
+        _ => None,
+    }
+}
+
+/// Lays out the items of an array the way `array_layout` picks, an exploded
+/// array gets a trailing comma and a collapsed one loses it.
+pub(crate) fn wrap_array(
+    l_blk: &Block,
+    r_blk: &Block,
+    options: &FormatOptions,
+) -> Option<WhiteSpace> {
+    let (array, after_comma) = array_between(l_blk, r_blk)?;
+    let is_close = r_blk.kind() == TomlKind::CloseBrace && r_blk.token().parent() == array;
+    let is_open = l_blk.kind() == TomlKind::OpenBrace && l_blk.token().parent() == array;
+
+    let value = match array_layout(&array, l_blk.indent_unit(), options) {
+        Layout::Keep => return None,
+        // an empty array is left alone
+        Layout::Exploded { .. } if is_open && is_close => return None,
+        Layout::Exploded { level, alignment } => {
+            if is_close && !after_comma {
+                r_blk.comma.set(CommaEdit::Insert);
+            }
+            SpaceValue::Indent {
+                level: if is_close { level - 1 } else { level },
+                alignment,
+                is_tab: false,
+            }
+        }
+        Layout::Flat => {
+            if is_close && after_comma {
+                l_blk.comma.set(CommaEdit::Remove);
+            }
+            if (is_open || is_close) && !options.space_inside_braces {
+                SpaceValue::None
+            } else {
+                SpaceValue::Single
+            }
+        }
+    };
+    let space = Space {
+        value,
+        loc: SpaceLoc::Before,
+    };
+    Some(WhiteSpace::from_rule(&space, l_blk, r_blk))
+}
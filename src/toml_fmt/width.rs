@@ -0,0 +1,155 @@
+use super::tkn_tree::{SyntaxNode, SyntaxNodeExtTrait, SyntaxToken, TomlKind};
+use super::ws::calc_indent;
+use super::FormatOptions;
+
+/// How the items of an array are laid out to fit `FormatOptions::max_width`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Layout {
+    /// The array is left to the other rules.
+    Keep,
+    /// All items on the line of the open brace.
+    Flat,
+    /// Each item on a line of its own indented `level` levels and
+    /// `alignment` columns, the close brace goes one level less.
+    Exploded { level: u32, alignment: u32 },
+}
+
+/// The indent level and alignment of the items below a line that starts
+/// with `indent`, `unit` spaces make one level.
+fn item_indent(indent: &str, unit: u32) -> (u32, u32) {
+    let (level, alignment) = calc_indent(&format!("\n{}", indent), unit);
+    (level + 1, alignment)
+}
+
+/// The column `tkn` starts at in the original text and the indentation of
+/// its line.
+fn column(tkn: &SyntaxToken) -> (usize, String) {
+    let mut col = 0;
+    let mut prev = tkn.prev_token();
+    while let Some(t) = prev {
+        let text = t.text().as_str();
+        if let Some(nl) = text.rfind('\n') {
+            let rest = &text[nl + 1..];
+            let indent = rest
+                .chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .collect::<String>();
+            return (col + rest.chars().count(), indent);
+        }
+        col += text.chars().count();
+        prev = t.prev_token();
+    }
+    (col, String::default())
+}
+
+/// The width of `array` written on one line.
+fn flat_width(array: &SyntaxNode, options: &FormatOptions) -> usize {
+    let items = array
+        .children()
+        .filter(|n| n.kind() == TomlKind::ArrayItem)
+        .filter_map(|item| item.children().find(|n| n.kind() == TomlKind::Value))
+        .map(|value| match value.first_child() {
+            Some(arr) if arr.kind() == TomlKind::Array => flat_width(&arr, options),
+            _ => {
+                let text = value.token_text();
+                // a multi-line string never fits
+                if text.trim().contains('\n') {
+                    usize::MAX / 2
+                } else {
+                    text.trim().chars().count()
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let pad = if options.space_inside_braces { 2 } else { 0 };
+    2 + pad + items.iter().sum::<usize>() + 2 * items.len().saturating_sub(1)
+}
+
+/// The column `array` starts at once formatted, the level its items would be
+/// indented to and the width of what follows it on its line. `outer` is the
+/// layout of the array `array` is an item of.
+fn position(
+    array: &SyntaxNode,
+    outer: Option<Layout>,
+    unit: u32,
+    options: &FormatOptions,
+) -> Option<(usize, (u32, u32), usize)> {
+    let parent = array.parent()?.parent()?;
+    match parent.kind() {
+        TomlKind::ArrayItem => match outer? {
+            Layout::Flat => None,
+            Layout::Exploded { level, alignment } => {
+                let col = (level * options.indent_width + alignment) as usize;
+                // the comma after the item
+                Some((col, (level + 1, alignment), 1))
+            }
+            Layout::Keep => {
+                let (col, indent) = column(&array.first_token()?);
+                Some((col, item_indent(&indent, unit), 1))
+            }
+        },
+        TomlKind::KeyValue => {
+            let key = parent.children().find(|n| n.kind() == TomlKind::Key)?;
+            let (_, indent) = column(&parent.first_token()?);
+            let col =
+                indent.chars().count() + key.token_text().trim().chars().count() + " = ".len();
+
+            let trailing = parent
+                .last_token()
+                .filter(|t| t.kind() == TomlKind::Whitespace)
+                .map(|t| t.text().to_string())
+                .unwrap_or_default();
+            let comment = parent
+                .next_sibling()
+                .filter(|n| n.kind() == TomlKind::Comment && !trailing.contains('\n'));
+            let suffix = comment
+                .map(|c| trailing.chars().count() + c.token_text().trim_end().chars().count())
+                .unwrap_or_default();
+            Some((col, item_indent(&indent, unit), suffix))
+        }
+        _ => None,
+    }
+}
+
+/// Picks how the items of `array` are laid out.
+///
+/// An array that does not fit within `max_width` is exploded, a multi-line
+/// array that fits is put on one line with `collapse_arrays`. Arrays holding
+/// comments and arrays inside inline tables, which must stay on one line,
+/// are kept as they are.
+pub(crate) fn array_layout(array: &SyntaxNode, unit: u32, options: &FormatOptions) -> Layout {
+    let max = match options.max_width {
+        Some(max) => max as usize,
+        None => return Layout::Keep,
+    };
+    if array.kind() != TomlKind::Array
+        || !array.children().any(|n| n.kind() == TomlKind::ArrayItem)
+        || array.descendants().any(|n| n.kind() == TomlKind::Comment)
+        || array.ancestors().any(|n| n.kind() == TomlKind::InlineTable)
+    {
+        return Layout::Keep;
+    }
+    let outer = array
+        .parent()
+        .and_then(|v| v.parent())
+        .filter(|item| item.kind() == TomlKind::ArrayItem)
+        .and_then(|item| item.parent())
+        .map(|outer| array_layout(&outer, unit, options));
+    // the items of an array on one line are on that line too
+    if outer == Some(Layout::Flat) {
+        return Layout::Flat;
+    }
+
+    let (col, (level, alignment), suffix) = match position(array, outer, unit, options) {
+        Some(pos) => pos,
+        None => return Layout::Keep,
+    };
+    let multi_line = array.token_text().contains('\n');
+    if col + flat_width(array, options) + suffix > max {
+        Layout::Exploded { level, alignment }
+    } else if multi_line && options.collapse_arrays {
+        Layout::Flat
+    } else {
+        Layout::Keep
+    }
+}
@@ -1,9 +1,5 @@
-use std::fmt;
-
-use super::tkn_tree::{SyntaxToken, TomlKind};
-use super::Block;
-
-pub(crate) const USER_INDENT_SIZE: u32 = 4;
+use super::tkn_tree::{walk::walk_tokens, SyntaxNode, SyntaxToken, TomlKind};
+use super::{Block, FormatOptions, IndentStyle};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -70,21 +66,21 @@ impl Space {
             value: SpaceValue::None,
         }
     }
-    fn before(token: SyntaxToken) -> Space {
+    fn before(token: SyntaxToken, unit: u32) -> Space {
         if !is_ws(&token) {
             return Self::empty_before();
         }
-        let value = calc_space_value(&token);
+        let value = calc_space_value(&token, unit);
         Self {
             loc: SpaceLoc::Before,
             value,
         }
     }
-    fn after(token: SyntaxToken) -> Space {
+    fn after(token: SyntaxToken, unit: u32) -> Space {
         if !is_ws(&token) {
             return Self::empty_after();
         }
-        let value = calc_space_value(&token);
+        let value = calc_space_value(&token, unit);
         Self {
             loc: SpaceLoc::After,
             value,
@@ -92,31 +88,45 @@ impl Space {
     }
 }
 
-impl fmt::Display for Space {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Space {
+    /// The text of the space with the line endings and indentation of
+    /// `options`.
+    pub(crate) fn render(&self, options: &FormatOptions) -> String {
+        let newline = options.line_ending();
         match self.value {
-            SpaceValue::Single => write!(f, " "),
-            SpaceValue::Newline => writeln!(f),
+            SpaceValue::Single => " ".into(),
+            SpaceValue::Newline => newline.into(),
             SpaceValue::Indent {
                 level,
                 alignment,
-                is_tab: false,
-            } => write!(
-                f,
-                "\n{}",
-                " ".repeat((level * USER_INDENT_SIZE + alignment) as usize)
-            ),
-            SpaceValue::Indent {
-                level,
-                is_tab: true,
-                ..
-            } => write!(f, "\n{}", "\t".repeat(level as usize)),
-            SpaceValue::MultiLF(count) => write!(f, "{}", "\n".repeat(count as usize)),
-            SpaceValue::MultiSpace(count) => write!(f, "{}", " ".repeat(count as usize)),
-            SpaceValue::None => write!(f, ""),
+                is_tab,
+            } => {
+                let tabs = match options.indent_style {
+                    IndentStyle::Preserve => is_tab,
+                    IndentStyle::Spaces => false,
+                    IndentStyle::Tabs => true,
+                };
+                if tabs {
+                    format!(
+                        "{}{}{}",
+                        newline,
+                        "\t".repeat(level as usize),
+                        " ".repeat(alignment as usize)
+                    )
+                } else {
+                    format!(
+                        "{}{}",
+                        newline,
+                        " ".repeat((level * options.indent_width + alignment) as usize)
+                    )
+                }
+            }
+            SpaceValue::MultiLF(count) => newline.repeat(count as usize),
+            SpaceValue::MultiSpace(count) => " ".repeat(count as usize),
+            SpaceValue::None => String::default(),
             _ => {
                 // unreachable!("no other writable variants")
-                write!(f, " {:?} ", self.value)
+                format!(" {:?} ", self.value)
             }
         }
     }
@@ -135,11 +145,13 @@ impl AsRef<WhiteSpace> for WhiteSpace {
 }
 
 impl WhiteSpace {
-    pub(crate) fn new(token: &SyntaxToken) -> WhiteSpace {
+    /// The whitespace around `token`, indents are measured in levels of
+    /// `unit` spaces, the indent unit of the document.
+    pub(crate) fn new(token: &SyntaxToken, unit: u32) -> WhiteSpace {
         let (space_before, space_after) = match (token.prev_token(), token.next_token()) {
-            (Some(pre), Some(post)) => (Space::before(pre), Space::after(post)),
-            (Some(pre), _) => (Space::before(pre), Space::empty_after()),
-            (_, Some(post)) => (Space::empty_before(), Space::after(post)),
+            (Some(pre), Some(post)) => (Space::before(pre, unit), Space::after(post, unit)),
+            (Some(pre), _) => (Space::before(pre, unit), Space::empty_after()),
+            (_, Some(post)) => (Space::empty_before(), Space::after(post, unit)),
             (_, _) => unreachable!("next or previous token returned a node"),
         };
 
@@ -197,8 +209,9 @@ impl WhiteSpace {
                 matches!(self.space_before.value, Single | Newline | Indent { .. })
             }
             // TODO make sure valid
-            Newline | NoneOrNewline | NoneOptionalNewline => {
-                matches!(self.space_before.value, Newline | Indent { .. })
+            Newline => matches!(self.space_before.value, Newline | Indent { .. }),
+            NoneOrNewline | NoneOptionalNewline => {
+                matches!(self.space_before.value, None | Newline | Indent { .. })
             }
             // TODO from here on the rules never set these they will
             // never be checked.
@@ -226,25 +239,37 @@ fn is_ws(token: &SyntaxToken) -> bool {
     token.kind() == TomlKind::Whitespace
 }
 
-pub(crate) fn calc_indent(ws: &str) -> (u32, u32) {
-    let len = if ws.contains("\n ") {
-        ws.matches(' ').count() as u32
-    } else if ws.contains('\t') {
-        ws.matches('\t').count() as u32 * USER_INDENT_SIZE
-    } else {
-        0
-    };
-    let level = len / USER_INDENT_SIZE;
-    let alignment = len % USER_INDENT_SIZE;
-    (level, alignment)
+/// The indent level and the columns past it of the whitespace `ws`, a tab
+/// is one level and `unit` spaces are one level.
+pub(crate) fn calc_indent(ws: &str, unit: u32) -> (u32, u32) {
+    let indent = ws.rsplit('\n').next().unwrap_or_default();
+    let tabs = indent.matches('\t').count() as u32;
+    let spaces = indent.matches(' ').count() as u32;
+    (tabs + spaces / unit, spaces % unit)
+}
+
+/// The number of spaces one indent level of `root` is made of, the smallest
+/// indent of any line indented with spaces only, `None` when no line is.
+pub(crate) fn indent_unit(root: &SyntaxNode) -> Option<u32> {
+    walk_tokens(root)
+        .filter(|tkn| is_ws(tkn) && tkn.text().contains('\n'))
+        .filter_map(|tkn| {
+            let indent = tkn.text().rsplit('\n').next()?;
+            if !indent.is_empty() && indent.chars().all(|c| c == ' ') {
+                Some(indent.len() as u32)
+            } else {
+                None
+            }
+        })
+        .min()
 }
 
-fn calc_space_value(tkn: &SyntaxToken) -> SpaceValue {
+fn calc_space_value(tkn: &SyntaxToken, unit: u32) -> SpaceValue {
     let orig = tkn.text().as_str();
     let tkn_len = orig.chars().count();
     // indent is `\n\s\s\s\s` or some variation
     if orig.contains('\n') && (orig.contains(' ') || orig.contains('\t')) {
-        let (level, alignment) = calc_indent(orig);
+        let (level, alignment) = calc_indent(orig, unit);
         SpaceValue::Indent {
             level,
             alignment,
@@ -0,0 +1,174 @@
+use std::fs::read_to_string;
+
+use toml_parse::{content_hash, diff, parse_it, semantic_eq, DiffKind, Formatter, KeySegment};
+
+fn path(segs: &[&str]) -> Vec<KeySegment> {
+    segs.iter()
+        .map(|s| match s.parse() {
+            Ok(idx) => KeySegment::Index(idx),
+            Err(_) => KeySegment::Key(s.to_string()),
+        })
+        .collect()
+}
+
+#[test]
+fn diff_formatting_only() {
+    let old = r#"# the package
+[package]
+name = "toml-parse"
+authors = [ "a", "b" ]
+dependencies.rowan = { version = "0.10" }
+"#;
+    let new = r#"[package]
+authors=["a","b"]
+name='toml-parse'
+
+[package.dependencies.rowan]
+version = "0.10"
+"#;
+    let old = parse_it(old).expect("parse failed").syntax();
+    let new = parse_it(new).expect("parse failed").syntax();
+    assert!(diff(&old, &new).unwrap().is_empty());
+}
+
+#[test]
+fn diff_keys_and_tables() {
+    let old_text = r#"[package]
+name = "a"
+version = "0.1.0"
+
+[dependencies]
+rowan = "0.10"
+"#;
+    let new_text = r#"[package]
+name = "a"
+version = "0.2.0"
+edition = "2018"
+
+[dev-dependencies]
+rowan = "0.10"
+"#;
+    let old = parse_it(old_text).expect("parse failed").syntax();
+    let new = parse_it(new_text).expect("parse failed").syntax();
+    let diffs = diff(&old, &new).unwrap();
+
+    let kinds = diffs
+        .iter()
+        .map(|d| (d.kind, d.path.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            (DiffKind::Changed, path(&["package", "version"])),
+            (DiffKind::Added, path(&["package", "edition"])),
+            (DiffKind::Removed, path(&["dependencies"])),
+            (DiffKind::Added, path(&["dev-dependencies"])),
+        ]
+    );
+
+    let changed = &diffs[0];
+    assert_eq!(&old_text[changed.old.unwrap()], "version = \"0.1.0\"");
+    assert_eq!(&new_text[changed.new.unwrap()], "version = \"0.2.0\"");
+    assert_eq!(diffs[1].old, None);
+    assert_eq!(&new_text[diffs[1].new.unwrap()], "edition = \"2018\"");
+    assert_eq!(
+        &old_text[diffs[2].old.unwrap()],
+        "[dependencies]\nrowan = \"0.10\""
+    );
+}
+
+#[test]
+fn diff_array_items() {
+    let old_text =
+        "members = [ \"a\", \"b\", \"c\" ]\n\n[[bin]]\nname = \"x\"\n\n[[bin]]\nname = \"y\"\n";
+    let new_text = "members = [ \"z\", \"a\", \"c\", \"d\" ]\n\n[[bin]]\nname = \"x\"\n\n[[bin]]\nname = \"w\"\n";
+    let old = parse_it(old_text).expect("parse failed").syntax();
+    let new = parse_it(new_text).expect("parse failed").syntax();
+    let diffs = diff(&old, &new).unwrap();
+
+    let kinds = diffs
+        .iter()
+        .map(|d| (d.kind, d.path.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            (DiffKind::Added, path(&["members", "0"])),
+            (DiffKind::Removed, path(&["members", "1"])),
+            (DiffKind::Added, path(&["members", "3"])),
+            (DiffKind::Changed, path(&["bin", "1", "name"])),
+        ]
+    );
+    assert_eq!(&old_text[diffs[1].old.unwrap()], "\"b\"");
+    assert_eq!(&new_text[diffs[3].new.unwrap()], "name = \"w\"");
+}
+
+#[test]
+fn semantic_eq_and_hash() {
+    let a = r#"# settings
+[server]
+host = "localhost"
+ports = [ 80, 443 ]
+started = 1979-05-27T07:32:00Z
+
+[server.tls]
+cert = 'a.pem'
+"#;
+    let b = r#"server.tls = { cert = "a.pem" }
+server.ports=[80,443]
+
+[server]
+started = 1979-05-27T00:32:00-07:00
+host   =   "localhost"
+"#;
+    let a = parse_it(a).expect("parse failed").syntax();
+    let b = parse_it(b).expect("parse failed").syntax();
+    assert!(semantic_eq(&a, &b).unwrap());
+    assert_eq!(content_hash(&a).unwrap(), content_hash(&b).unwrap());
+
+    let c = parse_it("[server]\nports = [ 443, 80 ]\n")
+        .expect("parse failed")
+        .syntax();
+    let d = parse_it("[server]\nports = [ 80, 443 ]\n")
+        .expect("parse failed")
+        .syntax();
+    assert!(!semantic_eq(&c, &d).unwrap());
+    assert_ne!(content_hash(&c).unwrap(), content_hash(&d).unwrap());
+
+    let e = parse_it("a = \"1\"").expect("parse failed").syntax();
+    let f = parse_it("a = 1").expect("parse failed").syntax();
+    assert!(!semantic_eq(&e, &f).unwrap());
+    assert_ne!(content_hash(&e).unwrap(), content_hash(&f).unwrap());
+}
+
+#[test]
+fn semantic_eq_nan() {
+    let a = parse_it("x = nan\ny = [ -inf, 1.5 ]\n")
+        .expect("parse failed")
+        .syntax();
+    let b = parse_it("x = -nan\ny = [-inf, 1.5]\n")
+        .expect("parse failed")
+        .syntax();
+    assert!(semantic_eq(&a, &a).unwrap());
+    assert!(semantic_eq(&a, &b).unwrap());
+    assert_eq!(content_hash(&a).unwrap(), content_hash(&b).unwrap());
+
+    let c = parse_it("x = inf\ny = [ -inf, 1.5 ]\n")
+        .expect("parse failed")
+        .syntax();
+    assert!(!semantic_eq(&a, &c).unwrap());
+    assert_ne!(content_hash(&a).unwrap(), content_hash(&c).unwrap());
+}
+
+#[test]
+fn semantic_eq_after_format() {
+    let input = read_to_string("examp/ftop.toml").expect("file not found");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+    let fmted = Formatter::new(&parsed).format().to_string();
+    let fmted = parse_it(&fmted).expect("parse failed").syntax();
+    assert!(semantic_eq(&parsed, &fmted).unwrap());
+    assert_eq!(
+        content_hash(&parsed).unwrap(),
+        content_hash(&fmted).unwrap()
+    );
+}
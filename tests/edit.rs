@@ -0,0 +1,482 @@
+use toml_parse::{
+    apply_edits, convert_table, insert_array_table, insert_key_value, insert_table, parse_it,
+    remove_key_value, remove_table, rename, replace_value, text_edits, Formatter, Matcher,
+    SyntaxNodeExtTrait, TableForm, TomlErrorKind,
+};
+
+const MATCHER: Matcher<'static> = Matcher {
+    heading: &["[dependencies]"],
+    segmented: &["dependencies."],
+    heading_key: &[],
+    array_table_key: &[],
+};
+
+#[test]
+fn replace_version() {
+    let file = r#"# the package
+[package]
+name = "toml-parse"
+version   =   "0.2.11"  # keep this
+edition = "2018"
+
+[dependencies]
+rowan = "0.10.0"
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let bumped = replace_value(&parsed, &["package", "version"], "\"0.3.0\"").unwrap();
+    assert_eq!(bumped.token_text(), file.replace("\"0.2.11\"", "\"0.3.0\""));
+}
+
+#[test]
+fn replace_inline_table_value() {
+    let file = "[dependencies]\nserde = {  version = \"1\", features = [\"derive\"] }\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let replaced = replace_value(
+        &parsed,
+        &["dependencies", "serde", "version"],
+        "\"1.0.100\"",
+    )
+    .unwrap();
+    assert_eq!(
+        replaced.token_text(),
+        "[dependencies]\nserde = {  version = \"1.0.100\", features = [\"derive\"] }\n"
+    );
+}
+
+#[test]
+fn replace_changes_type() {
+    let file = "[a.b]\nc = 1\nd = [ 1, 2 ]\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let replaced = replace_value(&parsed, &["a", "b", "c"], "[ true, false ]").unwrap();
+    assert_eq!(
+        replaced.token_text(),
+        "[a.b]\nc = [ true, false ]\nd = [ 1, 2 ]\n"
+    );
+}
+
+#[test]
+fn replace_missing_key() {
+    let file = "[package]\nname = \"a\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let err = replace_value(&parsed, &["package", "version"], "\"1\"").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &TomlErrorKind::KeyNotFound("package.version".into())
+    );
+}
+
+#[test]
+fn replace_invalid_value() {
+    let file = "[package]\nname = \"a\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    assert!(replace_value(&parsed, &["package", "name"], "\"a\" # b").is_err());
+    assert!(replace_value(&parsed, &["package", "name"], "").is_err());
+}
+
+#[test]
+fn insert_appends_to_table() {
+    let file = "[package]\nname = \"a\"\nedition = \"2018\" # keep\n\n[dependencies]\nrowan = \"0.10.0\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let added = insert_key_value(&parsed, &["package"], "version", "\"0.1.0\"", None).unwrap();
+    assert_eq!(
+        added.token_text(),
+        "[package]\nname = \"a\"\nedition = \"2018\" # keep\nversion = \"0.1.0\"\n\n[dependencies]\nrowan = \"0.10.0\"\n"
+    );
+}
+
+#[test]
+fn insert_sorted_with_matcher() {
+    let file = "[dependencies]\n  chrono    = \"0.4\"\n  rowan     = \"0.10.0\"\n\n[dev-dependencies]\nb = 1\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let first = insert_key_value(
+        &parsed,
+        &["dependencies"],
+        "anyhow",
+        "\"1\"",
+        Some(&MATCHER),
+    )
+    .unwrap();
+    let added = insert_key_value(
+        &first,
+        &["dependencies"],
+        "muncher",
+        "\"0.6\"",
+        Some(&MATCHER),
+    )
+    .unwrap();
+    assert_eq!(
+        added.token_text(),
+        "[dependencies]\n  anyhow    = \"1\"\n  chrono    = \"0.4\"\n  muncher   = \"0.6\"\n  rowan     = \"0.10.0\"\n\n[dev-dependencies]\nb = 1\n"
+    );
+}
+
+#[test]
+fn insert_into_inline_table() {
+    let file = "[dependencies]\nserde = { version = \"1\", default-features = false, }\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let added = insert_key_value(
+        &parsed,
+        &["dependencies", "serde"],
+        "features",
+        "[\"derive\"]",
+        Some(&MATCHER),
+    )
+    .unwrap();
+    assert_eq!(
+        added.token_text(),
+        "[dependencies]\nserde = { features = [\"derive\"], version = \"1\", default-features = false, }\n"
+    );
+    let added = insert_key_value(
+        &parsed,
+        &["dependencies", "serde"],
+        "optional",
+        "true",
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        added.token_text(),
+        "[dependencies]\nserde = { version = \"1\", default-features = false, optional = true, }\n"
+    );
+}
+
+#[test]
+fn insert_new_tables() {
+    let file = "[package]\nname = \"a\"\n\n[dependencies.rowan]\nversion = \"0.10.0\"\n\n[dependencies.chrono]\nversion = \"0.4\"\n\n[features]\ndefault = []\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+
+    let table = insert_table(&parsed, &["dependencies", "muncher"], None).unwrap();
+    assert_eq!(
+        table.token_text(),
+        file.replace("[features]", "[dependencies.muncher]\n\n[features]")
+    );
+
+    let bin = insert_array_table(&parsed, &["bin"], None).unwrap();
+    assert_eq!(bin.token_text(), format!("{}\n[[bin]]\n", file));
+    let bin = insert_array_table(&bin, &["bin"], None).unwrap();
+    assert_eq!(bin.token_text(), format!("{}\n[[bin]]\n\n[[bin]]\n", file));
+}
+
+#[test]
+fn insert_table_sorted_with_matcher() {
+    let file =
+        "[dependencies.chrono]\nversion = \"0.4\"\n\n[dependencies.rowan]\nversion = \"0.10.0\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let table = insert_table(&parsed, &["dependencies", "muncher"], Some(&MATCHER)).unwrap();
+    assert_eq!(
+        table.token_text(),
+        file.replace(
+            "[dependencies.rowan]",
+            "[dependencies.muncher]\n\n[dependencies.rowan]"
+        )
+    );
+}
+
+#[test]
+fn insert_into_last_array_table() {
+    let file = "[[bin]]\nname = \"a\"\npath = \"x\"\n\n[[bin]]\nname = \"b\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let added = insert_key_value(&parsed, &["bin"], "path", "\"y\"", None).unwrap();
+    assert_eq!(
+        added.token_text(),
+        "[[bin]]\nname = \"a\"\npath = \"x\"\n\n[[bin]]\nname = \"b\"\npath = \"y\"\n"
+    );
+
+    let err = insert_key_value(&added, &["bin"], "path", "\"z\"", None).unwrap_err();
+    assert_eq!(err.kind(), &TomlErrorKind::KeyExists("bin.path".into()));
+
+    let file = "[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n\n[bin.path]\nx = 1\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let err = insert_key_value(&parsed, &["bin"], "path", "\"y\"", None).unwrap_err();
+    assert_eq!(err.kind(), &TomlErrorKind::KeyExists("bin.path".into()));
+    assert!(insert_key_value(&parsed, &["bin"], "test", "true", None).is_ok());
+
+    let file = "[package]\nsite.url = \"a\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let err = insert_key_value(&parsed, &["package"], "site", "1", None).unwrap_err();
+    assert_eq!(err.kind(), &TomlErrorKind::KeyExists("package.site".into()));
+}
+
+#[test]
+fn insert_existing_key() {
+    let file = "[package]\nname = \"a\"\n\n[dependencies]\nrowan = \"0.10.0\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let err = insert_key_value(&parsed, &["package"], "name", "\"b\"", None).unwrap_err();
+    assert_eq!(err.kind(), &TomlErrorKind::KeyExists("package.name".into()));
+    let err = insert_table(&parsed, &["dependencies"], None).unwrap_err();
+    assert_eq!(err.kind(), &TomlErrorKind::KeyExists("dependencies".into()));
+    assert!(insert_key_value(&parsed, &["missing"], "a", "1", None).is_err());
+}
+
+#[test]
+fn remove_key_with_comments() {
+    let file = "[package]\nname = \"a\"\n\n# the version\n# bumped by ci\nversion = \"0.1.0\" # keep in sync\nedition = \"2018\"\n\n[dependencies]\nrowan = \"0.10.0\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let removed = remove_key_value(&parsed, &["package", "version"], false).unwrap();
+    assert_eq!(
+        removed.token_text(),
+        "[package]\nname = \"a\"\n\nedition = \"2018\"\n\n[dependencies]\nrowan = \"0.10.0\"\n"
+    );
+
+    let removed = remove_key_value(&parsed, &["package", "edition"], false).unwrap();
+    assert_eq!(
+        removed.token_text(),
+        "[package]\nname = \"a\"\n\n# the version\n# bumped by ci\nversion = \"0.1.0\" # keep in sync\n\n[dependencies]\nrowan = \"0.10.0\"\n"
+    );
+}
+
+#[test]
+fn remove_last_key_of_table() {
+    let file = "[package]\nname = \"a\"\n\n# runtime deps\n[dependencies]\nrowan = \"0.10.0\"\n\n[features]\ndefault = []\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+
+    let kept = remove_key_value(&parsed, &["dependencies", "rowan"], false).unwrap();
+    assert_eq!(
+        kept.token_text(),
+        "[package]\nname = \"a\"\n\n# runtime deps\n[dependencies]\n\n[features]\ndefault = []\n"
+    );
+
+    let removed = remove_key_value(&parsed, &["dependencies", "rowan"], true).unwrap();
+    assert_eq!(
+        removed.token_text(),
+        "[package]\nname = \"a\"\n\n[features]\ndefault = []\n"
+    );
+    let removed = remove_key_value(&removed, &["features", "default"], true).unwrap();
+    assert_eq!(removed.token_text(), "[package]\nname = \"a\"\n");
+}
+
+#[test]
+fn remove_tables() {
+    let file = "# the manifest\n\n[package]\nname = \"a\"\n\n[dependencies]\nrowan = \"0.10.0\"\n# dev only\n[dev-dependencies]\nb = 1\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+
+    let removed = remove_table(&parsed, &["dependencies"]).unwrap();
+    assert_eq!(
+        removed.token_text(),
+        "# the manifest\n\n[package]\nname = \"a\"\n\n# dev only\n[dev-dependencies]\nb = 1\n"
+    );
+    let removed = remove_table(&parsed, &["package"]).unwrap();
+    assert_eq!(
+        removed.token_text(),
+        "# the manifest\n\n[dependencies]\nrowan = \"0.10.0\"\n# dev only\n[dev-dependencies]\nb = 1\n"
+    );
+    assert!(remove_table(&parsed, &["features"]).is_err());
+}
+
+#[test]
+fn remove_inline_table_key() {
+    let file = "[dependencies]\nserde = { version = \"1\", features = [\"derive\"] }\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let removed = remove_key_value(&parsed, &["dependencies", "serde", "version"], true).unwrap();
+    assert_eq!(
+        removed.token_text(),
+        "[dependencies]\nserde = { features = [\"derive\"] }\n"
+    );
+}
+
+#[test]
+fn rename_everywhere() {
+    let file = r#"dev-dependencies.c = "3"
+
+[dev-dependencies]
+a = "1"
+
+[dev-dependencies."b.rs"]
+version = "2"
+
+[target.x86.dev-dependencies]
+d = { dev-dependencies = 1 }
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let renamed = rename(&parsed, &["dev-dependencies"], "dev_dependencies").unwrap();
+    assert_eq!(
+        renamed.token_text(),
+        r#"dev_dependencies.c = "3"
+
+[dev_dependencies]
+a = "1"
+
+[dev_dependencies."b.rs"]
+version = "2"
+
+[target.x86.dev-dependencies]
+d = { dev-dependencies = 1 }
+"#
+    );
+}
+
+#[test]
+fn rename_requotes_keys() {
+    let file = "[features]\ndefault = [\"std\"]\nstd = []\n\n[dependencies]\nserde = { version = \"1\", optional = true }\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+
+    let renamed = rename(&parsed, &["features", "std"], "use.std").unwrap();
+    assert_eq!(
+        renamed.token_text(),
+        file.replace("\nstd = []", "\n\"use.std\" = []")
+    );
+    let renamed = rename(&parsed, &["dependencies", "serde", "optional"], "opt").unwrap();
+    assert_eq!(
+        renamed.token_text(),
+        file.replace("optional = true", "opt = true")
+    );
+}
+
+#[test]
+fn rename_conflicts() {
+    let file = "[dependencies]\na = \"1\"\nc = \"3\"\n\n[dev-dependencies]\nb = \"2\"\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+
+    let err = rename(&parsed, &["dev-dependencies"], "dependencies").unwrap_err();
+    assert_eq!(err.kind(), &TomlErrorKind::KeyExists("dependencies".into()));
+    let err = rename(&parsed, &["dependencies", "a"], "c").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &TomlErrorKind::KeyExists("dependencies.c".into())
+    );
+    assert!(rename(&parsed, &["dependencies", "a"], "b").is_ok());
+    let err = rename(&parsed, &["features"], "feats").unwrap_err();
+    assert_eq!(err.kind(), &TomlErrorKind::KeyNotFound("features".into()));
+}
+
+fn assert_formatted(root: &toml_parse::SyntaxNode) {
+    let text = root.token_text();
+    let reparsed = parse_it(&text).expect("parse failed").syntax();
+    assert_eq!(Formatter::new(&reparsed).format().to_string(), text);
+}
+
+#[test]
+fn convert_inline_to_standard_and_back() {
+    let file = r#"[dependencies]
+chrono = "0.4"
+# derives for the ast
+serde = { version = "1", features = [ "derive" ] } # keep in sync
+
+[features]
+default = [ "std" ]
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let table = convert_table(&parsed, &["dependencies", "serde"], TableForm::Standard).unwrap();
+    assert_eq!(
+        table.token_text(),
+        r#"[dependencies]
+chrono = "0.4"
+
+# derives for the ast
+# keep in sync
+[dependencies.serde]
+version = "1"
+features = [ "derive" ]
+
+[features]
+default = [ "std" ]
+"#
+    );
+    assert_formatted(&table);
+
+    let inline = convert_table(&table, &["dependencies", "serde"], TableForm::Inline).unwrap();
+    assert_eq!(
+        inline.token_text(),
+        r#"[dependencies]
+chrono = "0.4"
+# derives for the ast
+# keep in sync
+serde = { version = "1", features = [ "derive" ] }
+
+[features]
+default = [ "std" ]
+"#
+    );
+    assert_formatted(&inline);
+}
+
+#[test]
+fn convert_dotted_keys() {
+    let file = r#"name = "a"
+site.url = "b"
+# where to deploy
+site.branch = "main"
+
+[package]
+edition = "2018"
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let table = convert_table(&parsed, &["site"], TableForm::Standard).unwrap();
+    assert_eq!(
+        table.token_text(),
+        r#"name = "a"
+
+[package]
+edition = "2018"
+
+# where to deploy
+[site]
+url = "b"
+branch = "main"
+"#
+    );
+    assert_formatted(&table);
+
+    let dotted = convert_table(&table, &["site"], TableForm::Dotted).unwrap();
+    assert_eq!(
+        dotted.token_text(),
+        r#"name = "a"
+# where to deploy
+site.url = "b"
+site.branch = "main"
+
+[package]
+edition = "2018"
+"#
+    );
+    assert_formatted(&dotted);
+
+    let inline = convert_table(&parsed, &["site"], TableForm::Inline).unwrap();
+    assert_eq!(
+        inline.token_text(),
+        "name = \"a\"\n# where to deploy\nsite = { url = \"b\", branch = \"main\" }\n\n[package]\nedition = \"2018\"\n"
+    );
+}
+
+#[test]
+fn convert_with_sub_tables() {
+    let file = "[a]\nb = 1\n\n[a.c]\nd = 2\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let err = convert_table(&parsed, &["a"], TableForm::Inline).unwrap_err();
+    assert_eq!(err.kind(), &TomlErrorKind::KeyExists("a.c".into()));
+
+    let inline = convert_table(&parsed, &["a", "c"], TableForm::Inline).unwrap();
+    assert_eq!(inline.token_text(), "[a]\nb = 1\nc = { d = 2 }\n");
+    assert!(convert_table(&parsed, &["x"], TableForm::Dotted).is_err());
+}
+
+#[test]
+fn edits_for_every_change() {
+    let file = r#"[package]
+name = "a"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1" }
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let changed = vec![
+        replace_value(&parsed, &["package", "version"], "\"0.2.0\"").unwrap(),
+        insert_key_value(&parsed, &["package"], "edition", "\"2018\"", None).unwrap(),
+        insert_table(&parsed, &["features"], None).unwrap(),
+        remove_key_value(&parsed, &["package", "name"], false).unwrap(),
+        remove_table(&parsed, &["dependencies"]).unwrap(),
+        rename(&parsed, &["dependencies"], "dev-dependencies").unwrap(),
+        convert_table(&parsed, &["dependencies", "serde"], TableForm::Standard).unwrap(),
+    ];
+    for new in changed {
+        let edits = text_edits(&parsed, &new);
+        assert_eq!(apply_edits(file, &edits), new.token_text());
+    }
+
+    let renamed = rename(&parsed, &["package", "name"], "label").unwrap();
+    let edits = text_edits(&parsed, &renamed);
+    assert_eq!(edits.len(), 1);
+    assert_eq!(&file[edits[0].range], "name");
+    assert_eq!(edits[0].replacement, "label");
+}
@@ -1,6 +1,6 @@
 use std::fs::read_to_string;
 
-use toml_parse::{parse_it, Formatter};
+use toml_parse::{apply_edits, parse_it, FormatOptions, Formatter, IndentStyle, NewlineStyle};
 
 #[test]
 fn fmt_eq_space() {
@@ -31,6 +31,13 @@ fn fmt_comma_arr() {
     assert_eq!(fmt.to_string(), "key = [ 1, 2, 3 ]\n")
 }
 #[test]
+fn fmt_arr_comments() {
+    let file = "key = [\n    # first\n    \"b\", # bee\n    \"a\",\n]\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let fmt = Formatter::new(&parsed).format();
+    assert_eq!(fmt.to_string(), file)
+}
+#[test]
 fn fmt_comma_obj() {
     let file = "key={a=1,b=2}";
     let parsed = parse_it(file).expect("parse failed").syntax();
@@ -95,3 +102,123 @@ fn fmt_clippy() {
 
     assert_eq!(fmted.to_string(), fixed);
 }
+
+#[test]
+fn fmt_text_edits() {
+    let file = "[table]\nkey   =  false [other]\narr = [1,2]";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let fmted = Formatter::new(&parsed).format();
+    let edits = fmted.text_edits();
+    assert_eq!(apply_edits(file, &edits), fmted.to_string());
+    // only whitespace is touched
+    assert!(edits
+        .iter()
+        .all(|e| file[e.range].trim().is_empty() && e.replacement.trim().is_empty()));
+
+    let input = read_to_string("examp/fend.toml").expect("file read failed");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+    let fmted = Formatter::new(&parsed).format();
+    assert_eq!(apply_edits(&input, &fmted.text_edits()), fmted.to_string());
+}
+#[test]
+fn fmt_options() {
+    let file = "[a]\nkey = { x = 1,y = 2 }\n[b]\narr = [\n\t1,\n  2,\n]\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let options = FormatOptions {
+        indent_width: 2,
+        indent_style: IndentStyle::Spaces,
+        newline: NewlineStyle::Windows,
+        trailing_newline: false,
+        blank_lines_between_tables: 2,
+        space_inside_braces: false,
+        ..FormatOptions::default()
+    };
+    let fmted = Formatter::with_options(&parsed, options.clone()).format();
+    let expected = "[a]\r\nkey = {x = 1, y = 2}\r\n\r\n\r\n[b]\r\narr = [\r\n  1,\r\n  2,\r\n]";
+    assert_eq!(fmted.to_string(), expected);
+    assert_eq!(apply_edits(file, &fmted.text_edits()), expected);
+
+    let reparsed = parse_it(expected).expect("parse failed").syntax();
+    let again = Formatter::with_options(&reparsed, options).format();
+    assert_eq!(again.to_string(), expected);
+}
+#[test]
+fn fmt_options_tabs_auto_newline() {
+    let file = "[a]\r\narr = [\r\n    1,\r\n    2,\r\n]\r\n[b]\r\nc = 1";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let options = FormatOptions {
+        indent_style: IndentStyle::Tabs,
+        newline: NewlineStyle::Auto,
+        blank_lines_between_tables: 0,
+        ..FormatOptions::default()
+    };
+    let fmted = Formatter::with_options(&parsed, options).format();
+    assert_eq!(
+        fmted.to_string(),
+        "[a]\r\narr = [\r\n\t1,\r\n\t2,\r\n]\r\n[b]\r\nc = 1\r\n"
+    );
+}
+#[test]
+fn fmt_options_reindent() {
+    let file = "arr = [\n    1,\n    2,\n]\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let options = FormatOptions {
+        indent_width: 2,
+        indent_style: IndentStyle::Spaces,
+        ..FormatOptions::default()
+    };
+    let fmted = Formatter::with_options(&parsed, options.clone()).format();
+    let expected = "arr = [\n  1,\n  2,\n]\n";
+    assert_eq!(fmted.to_string(), expected);
+    assert_eq!(apply_edits(file, &fmted.text_edits()), expected);
+
+    let reparsed = parse_it(expected).expect("parse failed").syntax();
+    let again = Formatter::with_options(&reparsed, options.clone()).format();
+    assert_eq!(again.to_string(), expected);
+
+    let tabs = FormatOptions {
+        indent_style: IndentStyle::Tabs,
+        ..options
+    };
+    let fmted = Formatter::with_options(&parsed, tabs).format();
+    assert_eq!(fmted.to_string(), "arr = [\n\t1,\n\t2,\n]\n");
+}
+#[test]
+fn fmt_max_width() {
+    let file =
+        "[workspace]\nmembers = [\"alpha\", \"beta\", \"gamma\"]\nshort = [\n    1,\n    2,\n]\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let options = FormatOptions {
+        max_width: Some(30),
+        ..FormatOptions::default()
+    };
+    let fmted = Formatter::with_options(&parsed, options.clone()).format();
+    let expected = "[workspace]\nmembers = [\n    \"alpha\",\n    \"beta\",\n    \"gamma\",\n]\nshort = [\n    1,\n    2,\n]\n";
+    assert_eq!(fmted.to_string(), expected);
+    assert_eq!(apply_edits(file, &fmted.text_edits()), expected);
+
+    let collapse = FormatOptions {
+        collapse_arrays: true,
+        ..options
+    };
+    let fmted = Formatter::with_options(&parsed, collapse).format();
+    let expected = "[workspace]\nmembers = [\n    \"alpha\",\n    \"beta\",\n    \"gamma\",\n]\nshort = [ 1, 2 ]\n";
+    assert_eq!(fmted.to_string(), expected);
+    assert_eq!(apply_edits(file, &fmted.text_edits()), expected);
+}
+#[test]
+fn fmt_max_width_nested() {
+    let file = "a = [[1, 2], [3, 4, 5, 6, 7, 8, 9]]\nb = [ # keep\n  1, 2 ]\nc = { d = [1, 2, 3, 4, 5, 6] }\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let options = FormatOptions {
+        max_width: Some(28),
+        ..FormatOptions::default()
+    };
+    let fmted = Formatter::with_options(&parsed, options.clone()).format();
+    let expected = "a = [\n    [ 1, 2 ],\n    [ 3, 4, 5, 6, 7, 8, 9 ],\n]\nb = [ # keep\n  1, 2 ]\nc = { d = [ 1, 2, 3, 4, 5, 6 ] }\n";
+    assert_eq!(fmted.to_string(), expected);
+
+    let reparsed = parse_it(expected).expect("parse failed").syntax();
+    let again = Formatter::with_options(&reparsed, options).format();
+    assert_eq!(again.to_string(), expected);
+}
@@ -0,0 +1,169 @@
+use toml_parse::{merge, parse_it, SyntaxNodeExtTrait};
+
+#[test]
+fn merge_independent_changes() {
+    let base = r#"# the package
+[package]
+name = "a"
+version = "0.1.0"
+
+[dependencies]
+rowan = "0.10"
+"#;
+    let ours = r#"# the package
+[package]
+name = "a"
+version   =   "0.2.0"
+
+[dependencies]
+rowan = "0.10"
+"#;
+    let theirs = r#"# the package
+[package]
+name = "a"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+rowan = "0.10"
+
+[dev-dependencies]
+# for the tests
+chrono = "0.4"
+"#;
+    let base = parse_it(base).expect("parse failed").syntax();
+    let ours = parse_it(ours).expect("parse failed").syntax();
+    let theirs = parse_it(theirs).expect("parse failed").syntax();
+
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert!(merged.conflicts.is_empty());
+    assert_eq!(
+        merged.root.token_text(),
+        r#"# the package
+[package]
+name = "a"
+version   =   "0.2.0"
+edition   =   "2018"
+
+[dependencies]
+rowan = "0.10"
+
+[dev-dependencies]
+# for the tests
+chrono = "0.4"
+"#
+    );
+}
+
+#[test]
+fn merge_removed_and_changed_values() {
+    let base = "[package]\nname = \"a\"\nauthors = [ \"x\" ]\n\n[features]\ndefault = []\n";
+    let ours = "[package]\nname = \"b\"\nauthors = [ \"x\" ]\n\n[features]\ndefault = []\n";
+    let theirs = "[package]\nname = \"a\"\nauthors = [ \"x\", \"y\" ]\n";
+    let base = parse_it(base).expect("parse failed").syntax();
+    let ours = parse_it(ours).expect("parse failed").syntax();
+    let theirs = parse_it(theirs).expect("parse failed").syntax();
+
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert!(merged.conflicts.is_empty());
+    assert_eq!(
+        merged.root.token_text(),
+        "[package]\nname = \"b\"\nauthors = [ \"x\", \"y\" ]\n"
+    );
+}
+
+#[test]
+fn merge_same_change_on_both_sides() {
+    let base = "[package]\nversion = \"0.1.0\"\n";
+    let ours = "[package]\nversion = \"0.2.0\"\n";
+    let theirs = "[package]\nversion = '0.2.0'\n";
+    let base = parse_it(base).expect("parse failed").syntax();
+    let ours = parse_it(ours).expect("parse failed").syntax();
+    let theirs = parse_it(theirs).expect("parse failed").syntax();
+
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert!(merged.conflicts.is_empty());
+    assert_eq!(merged.root.token_text(), "[package]\nversion = \"0.2.0\"\n");
+}
+
+#[test]
+fn merge_conflict_markers() {
+    let base = "[package]\nname = \"a\"\nversion = \"0.1.0\"\n";
+    let ours = "[package]\nname = \"a\"\nversion = \"0.2.0\"\n";
+    let theirs = "[package]\nname = \"c\"\nversion = \"0.3.0\"\n";
+    let base = parse_it(base).expect("parse failed").syntax();
+    let ours = parse_it(ours).expect("parse failed").syntax();
+    let theirs = parse_it(theirs).expect("parse failed").syntax();
+
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert_eq!(merged.conflicts.len(), 1);
+    assert_eq!(merged.conflicts[0].path, vec!["package", "version"]);
+    assert_eq!(
+        merged.conflicts[0].theirs.as_deref(),
+        Some("version = \"0.3.0\"")
+    );
+    assert_eq!(
+        merged.root.token_text(),
+        "[package]\nname = \"c\"\nversion = \"0.2.0\"\n"
+    );
+    assert_eq!(
+        merged.with_conflict_markers().unwrap(),
+        "[package]\nname = \"c\"\n<<<<<<< ours\nversion = \"0.2.0\"\n=======\nversion = \"0.3.0\"\n>>>>>>> theirs\n"
+    );
+}
+
+#[test]
+fn merge_conflict_with_removal() {
+    let base = "[package]\nname = \"a\"\n\n[badges]\nci = \"x\"\n";
+    let ours = "[package]\nname = \"a\"\n";
+    let theirs = "[package]\nname = \"a\"\n\n[badges]\nci = \"y\"\n";
+    let base = parse_it(base).expect("parse failed").syntax();
+    let ours = parse_it(ours).expect("parse failed").syntax();
+    let theirs = parse_it(theirs).expect("parse failed").syntax();
+
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert_eq!(merged.conflicts.len(), 1);
+    assert_eq!(merged.conflicts[0].ours, None);
+    assert_eq!(
+        merged.with_conflict_markers().unwrap(),
+        "[package]\nname = \"a\"\n<<<<<<< ours\n=======\n[badges]\nci = \"y\"\n>>>>>>> theirs\n"
+    );
+}
+
+#[test]
+fn merge_conflict_under_removed_table() {
+    let base = "[package]\nversion = \"0.1.0\"\nedition = \"2015\"\n\n[lib]\npath = \"a\"\n";
+    let ours = "[lib]\npath = \"a\"\n";
+    let theirs = "[package]\nversion = \"0.2.0\"\nedition = \"2018\"\n\n[lib]\npath = \"a\"\n";
+    let base = parse_it(base).expect("parse failed").syntax();
+    let ours = parse_it(ours).expect("parse failed").syntax();
+    let theirs = parse_it(theirs).expect("parse failed").syntax();
+
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert_eq!(merged.conflicts.len(), 1);
+    assert_eq!(merged.conflicts[0].path, vec!["package"]);
+    assert_eq!(merged.conflicts[0].ours, None);
+    assert_eq!(
+        merged.with_conflict_markers().unwrap(),
+        "[lib]\npath = \"a\"\n<<<<<<< ours\n=======\n[package]\nversion = \"0.2.0\"\nedition = \"2018\"\n>>>>>>> theirs\n"
+    );
+}
+
+#[test]
+fn merge_markers_for_removed_tables() {
+    let base = "[package]\nversion = \"0.1.0\"\nedition = \"2015\"\n\n[badges]\nci = \"x\"\n";
+    let ours = "";
+    let theirs = "[package]\nversion = \"0.2.0\"\nedition = \"2018\"\n\n[badges]\nci = \"y\"\n";
+    let base = parse_it(base).expect("parse failed").syntax();
+    let ours = parse_it(ours).expect("parse failed").syntax();
+    let theirs = parse_it(theirs).expect("parse failed").syntax();
+
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert_eq!(merged.conflicts.len(), 2);
+    let markers = merged.with_conflict_markers().unwrap();
+    assert_eq!(
+        markers,
+        "\n<<<<<<< ours\n=======\n[package]\nversion = \"0.2.0\"\nedition = \"2018\"\n>>>>>>> theirs\n<<<<<<< ours\n=======\n[badges]\nci = \"y\"\n>>>>>>> theirs"
+    );
+    assert_eq!(markers.matches("<<<<<<<").count(), 2);
+}
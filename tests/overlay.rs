@@ -0,0 +1,198 @@
+use toml_parse::{overlay, parse_it, ArrayStrategy, KeySegment, Layer, SyntaxNodeExtTrait};
+
+fn path(segs: &[&str]) -> Vec<KeySegment> {
+    segs.iter()
+        .map(|s| match s.parse() {
+            Ok(idx) => KeySegment::Index(idx),
+            Err(_) => KeySegment::Key(s.to_string()),
+        })
+        .collect()
+}
+
+#[test]
+fn overlay_scalars_and_tables() {
+    let base = r#"# shared settings
+[server]
+host = "localhost" # overridden per env
+port = 8080
+
+[log]
+level = "info"
+"#;
+    let prod = r#"[server]
+host = "example.com"
+tls = { cert = "a.pem" }
+
+[log]
+level = "info"
+
+[metrics]
+enabled = true
+"#;
+    let base = parse_it(base).expect("parse failed").syntax();
+    let prod = parse_it(prod).expect("parse failed").syntax();
+    let merged = overlay(&base, &prod, &ArrayStrategy::Replace).unwrap();
+    assert_eq!(
+        merged.root.token_text(),
+        r#"# shared settings
+[server]
+host = "example.com" # overridden per env
+port = 8080
+tls = { cert = "a.pem" }
+
+[log]
+level = "info"
+
+[metrics]
+enabled = true
+"#
+    );
+
+    let layers = merged.provenance.into_iter().collect::<Vec<_>>();
+    assert_eq!(
+        layers,
+        vec![
+            (path(&["log", "level"]), Layer::Overlay),
+            (path(&["metrics", "enabled"]), Layer::Overlay),
+            (path(&["server", "host"]), Layer::Overlay),
+            (path(&["server", "port"]), Layer::Base),
+            (path(&["server", "tls", "cert"]), Layer::Overlay),
+        ]
+    );
+}
+
+#[test]
+fn overlay_array_strategies() {
+    let base = "features = [\n    \"a\",\n    \"b\",\n]\n";
+    let layer = "features = [ \"b\", \"c\" ]\n";
+    let base = parse_it(base).expect("parse failed").syntax();
+    let layer = parse_it(layer).expect("parse failed").syntax();
+
+    let replaced = overlay(&base, &layer, &ArrayStrategy::Replace).unwrap();
+    assert_eq!(replaced.root.token_text(), "features = [ \"b\", \"c\" ]\n");
+
+    let appended = overlay(&base, &layer, &ArrayStrategy::Append).unwrap();
+    assert_eq!(
+        appended.root.token_text(),
+        "features = [\n    \"a\",\n    \"b\",\n    \"b\",\n    \"c\",\n]\n"
+    );
+
+    let union = overlay(&base, &layer, &ArrayStrategy::UnionBy("name".into())).unwrap();
+    assert_eq!(
+        union.root.token_text(),
+        "features = [\n    \"a\",\n    \"b\",\n    \"c\",\n]\n"
+    );
+    let layers = union.provenance.into_iter().collect::<Vec<_>>();
+    assert_eq!(
+        layers,
+        vec![
+            (path(&["features", "0"]), Layer::Base),
+            (path(&["features", "1"]), Layer::Overlay),
+            (path(&["features", "2"]), Layer::Overlay),
+        ]
+    );
+}
+
+#[test]
+fn overlay_array_of_tables_by_key() {
+    let base = r#"[[bin]]
+name = "server"
+path = "src/main.rs"
+
+[[bin]]
+# the cli
+name = "cli"
+"#;
+    let layer = r#"[[bin]]
+name = "cli"
+path = "src/cli.rs"
+
+[[bin]]
+name = "tool"
+"#;
+    let base = parse_it(base).expect("parse failed").syntax();
+    let layer = parse_it(layer).expect("parse failed").syntax();
+    let merged = overlay(&base, &layer, &ArrayStrategy::UnionBy("name".into())).unwrap();
+    assert_eq!(
+        merged.root.token_text(),
+        r#"[[bin]]
+name = "server"
+path = "src/main.rs"
+
+[[bin]]
+# the cli
+name = "cli"
+path = "src/cli.rs"
+
+[[bin]]
+name = "tool"
+"#
+    );
+    assert_eq!(merged.provenance[&path(&["bin", "0", "name"])], Layer::Base);
+    assert_eq!(
+        merged.provenance[&path(&["bin", "1", "path"])],
+        Layer::Overlay
+    );
+}
+
+#[test]
+fn overlay_array_of_tables_with_sub_tables() {
+    let base = r#"[[services]]
+name = "web"
+port = 80
+
+[services.env]
+LOG = "info"
+
+[[services]]
+name = "db"
+"#;
+    let layer = r#"[[services]]
+name = "web"
+
+[services.env]
+LOG = "debug"
+TRACE = "1"
+
+[[services]]
+name = "cache"
+
+[services.env]
+SIZE = "64"
+"#;
+    let base = parse_it(base).expect("parse failed").syntax();
+    let layer = parse_it(layer).expect("parse failed").syntax();
+    let merged = overlay(&base, &layer, &ArrayStrategy::UnionBy("name".into())).unwrap();
+    assert_eq!(
+        merged.root.token_text(),
+        r#"[[services]]
+name = "web"
+port = 80
+
+[services.env]
+LOG = "debug"
+TRACE = "1"
+
+[[services]]
+name = "db"
+
+[[services]]
+name = "cache"
+
+[services.env]
+SIZE = "64"
+"#
+    );
+    assert_eq!(
+        merged.provenance[&path(&["services", "0", "port"])],
+        Layer::Base
+    );
+    assert_eq!(
+        merged.provenance[&path(&["services", "0", "env", "LOG"])],
+        Layer::Overlay
+    );
+    assert_eq!(
+        merged.provenance[&path(&["services", "2", "env", "SIZE"])],
+        Layer::Overlay
+    );
+}
@@ -10,6 +10,14 @@ fn parse_table_comment() {
     assert_eq!(root.token_text(), file)
 }
 
+#[test]
+fn parse_array_comments() {
+    let file = "key = [\n    # first\n    \"b\", # bee\n    \"a\" # a\n    # end\n]\n";
+    let parsed = parse_it(file).expect("parse failed");
+    let root = parsed.syntax();
+    assert_eq!(root.token_text(), file)
+}
+
 #[test]
 fn parse_single_quote_key() {
     let file = "[table]\n'key' = \"value\"";
@@ -1,11 +1,16 @@
 use std::fs::read_to_string;
 
-use toml_parse::{parse_it, sort_toml_items, walk, Matcher, SyntaxNode, SyntaxNodeExtTrait};
+use toml_parse::{
+    apply_edits, check_sorted, order_tables, parse_it, sort_array_tables, sort_in_place,
+    sort_toml_items, text_edits, walk, ArrayOrder, KeyOrder, Matcher, SortConfig, SyntaxNode,
+    SyntaxNodeExtTrait, ViolationKind, CARGO_TABLE_ORDER,
+};
 
 const HEADER: Matcher<'static> = Matcher {
     heading: &["[dependencies]"],
     segmented: &["dependencies."],
     heading_key: &[("[workspace]", "members")],
+    array_table_key: &[],
 };
 
 #[allow(dead_code)]
@@ -58,7 +63,25 @@ fn sort_tkns_seg() {
 
     let sorted = sort_toml_items(&parsed, &HEADER);
 
+    // each run of `dependencies.` tables is already in order by full path
+    assert!(parsed.deep_eq(&sorted));
+    assert_eq!(sorted.token_text(), input);
+    assert_eq!(sorted.text_range(), parsed.text_range());
+}
+
+#[test]
+fn sort_tkns_seg_path() {
+    let input = read_to_string("examp/seg_sort_path.toml").expect("file read failed");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+
+    let sorted = sort_toml_items(&parsed, &HEADER);
+
     assert!(!parsed.deep_eq(&sorted));
+    // the blank line after a table moves with it
+    assert_eq!(
+        sorted.token_text(),
+        "[dependencies.three.last]\nversion = \"0.15\"\n\n[dependencies.two.alpha]\nversion = \"0.15\"\n\n[hello]\n\n[dependencies.four.gama]\nversion = \"0.15\"\n[dependencies.one.beta]\nversion = \"0.15\"\n\n"
+    );
     assert_eq!(sorted.text_range(), parsed.text_range());
 }
 
@@ -159,6 +182,7 @@ fn sort_clippy() {
         heading: &HEADERS,
         segmented: &HEADER_SEG,
         heading_key: &[("[workspace]", "members"), ("[workspace]", "exclude")],
+        array_table_key: &[],
     };
 
     let input = read_to_string("examp/clippy.toml").expect("file read failed");
@@ -173,3 +197,725 @@ fn sort_clippy() {
     // assert!(parsed.deep_eq(&sorted));
     assert_eq!(sorted.text_range(), parsed.text_range());
 }
+
+#[test]
+fn sort_text_edits() {
+    let input = read_to_string("examp/seg_sort.toml").expect("file read failed");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+    let sorted = sort_toml_items(&parsed, &HEADER);
+
+    // already sorted, nothing to edit
+    assert!(text_edits(&parsed, &sorted).is_empty());
+    assert_eq!(apply_edits(&input, &[]), sorted.token_text());
+}
+
+#[test]
+fn sort_text_edits_seg_path() {
+    let input = read_to_string("examp/seg_sort_path.toml").expect("file read failed");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+    let sorted = sort_toml_items(&parsed, &HEADER);
+
+    let edits = text_edits(&parsed, &sorted);
+    assert!(!edits.is_empty());
+    assert!(edits
+        .windows(2)
+        .all(|pair| pair[0].range.end() <= pair[1].range.start()));
+    assert_eq!(apply_edits(&input, &edits), sorted.token_text());
+    assert!(text_edits(&sorted, &sorted).is_empty());
+}
+
+/// Whether the keys appear in `text` in the given order.
+fn in_order(text: &str, keys: &[&str]) -> bool {
+    let pos = keys
+        .iter()
+        .map(|k| text.find(&format!("{} = ", k)).unwrap())
+        .collect::<Vec<_>>();
+    pos.windows(2).all(|w| w[0] < w[1])
+}
+
+#[test]
+fn sort_matcher_modes() {
+    let file = r#"[dependencies]
+b = "1"
+a = "1"
+
+[target.'cfg(unix)'.dependencies]
+d = "1"
+c = "1"
+
+[dev-dependencies]
+f = "1"
+e = "1"
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let sorted_with = |heading: &[&str]| {
+        let matcher = Matcher {
+            heading,
+            segmented: &[],
+            heading_key: &[],
+            array_table_key: &[],
+        };
+        sort_toml_items(&parsed, &matcher).token_text()
+    };
+
+    let exact = sorted_with(&["exact:[dependencies]"]);
+    assert!(in_order(&exact, &["a", "b", "d", "c", "f", "e"]));
+
+    let glob = sorted_with(&["glob:[*-dependencies]"]);
+    assert!(in_order(&glob, &["b", "a", "d", "c", "e", "f"]));
+
+    let prefix = sorted_with(&["prefix:[target."]);
+    assert!(in_order(&prefix, &["b", "a", "c", "d", "f", "e"]));
+
+    let regex = sorted_with(&[r"regex:^\[.*dependencies\]$"]);
+    assert!(in_order(&regex, &["a", "b", "c", "d", "e", "f"]));
+}
+
+#[test]
+fn sort_matcher_segmented_and_key_modes() {
+    let file = r#"[workspace]
+members = [ "b", "a" ]
+exclude-members = [ "d", "c" ]
+
+[dev-dependencies.b]
+version = "1"
+
+[dev-dependencies.a]
+version = "1"
+"#;
+    const MATCHER: Matcher<'static> = Matcher {
+        heading: &[],
+        segmented: &["glob:*dependencies.*"],
+        heading_key: &[("exact:[workspace]", "exact:members")],
+        array_table_key: &[],
+    };
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let sorted = sort_toml_items(&parsed, &MATCHER).token_text();
+    assert!(sorted.find("\"a\"").unwrap() < sorted.find("\"b\"").unwrap());
+    assert!(sorted.find("\"d\"").unwrap() < sorted.find("\"c\"").unwrap());
+    assert!(
+        sorted.find("[dev-dependencies.a]").unwrap() < sorted.find("[dev-dependencies.b]").unwrap()
+    );
+    assert!(MATCHER.check().is_ok());
+
+    let invalid = Matcher {
+        heading: &["regex:[unclosed"],
+        segmented: &[],
+        heading_key: &[],
+        array_table_key: &[],
+    };
+    assert!(invalid.check().is_err());
+}
+
+#[test]
+fn sort_config_from_file() {
+    const HEADERS: [&str; 3] = [
+        "[dependencies]",
+        "[dev-dependencies]",
+        "[build-dependencies]",
+    ];
+    const HEADER_SEG: [&str; 3] = ["dependencies.", "dev-dependencies.", "build-dependencies."];
+    const MATCHER: Matcher<'_> = Matcher {
+        heading: &HEADERS,
+        segmented: &HEADER_SEG,
+        heading_key: &[("[workspace]", "members"), ("[workspace]", "exclude")],
+        array_table_key: &[],
+    };
+
+    let config = SortConfig::from_file("examp/sort_config.toml").expect("invalid config");
+    assert_eq!(config, SortConfig::from(&MATCHER));
+
+    let input = read_to_string("examp/clippy.toml").expect("file read failed");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+    assert!(config
+        .sort(&parsed)
+        .deep_eq(&sort_toml_items(&parsed, &MATCHER)));
+}
+
+#[test]
+fn sort_config_builder() {
+    let config = SortConfig::builder()
+        .heading("exact:[dependencies]")
+        .segmented("prefix:dependencies.")
+        .heading_key("[workspace]", "members")
+        .build()
+        .unwrap();
+    let loaded = SortConfig::from_toml(
+        r#"heading = [ "exact:[dependencies]" ]
+segmented = [ "prefix:dependencies." ]
+heading_key = [ { heading = "[workspace]", key = "members" } ]
+"#,
+    )
+    .unwrap();
+    assert_eq!(config, loaded);
+
+    assert!(SortConfig::builder().heading("regex:(").build().is_err());
+    assert!(SortConfig::from_toml("headings = [ \"[dependencies]\" ]").is_err());
+    assert!(SortConfig::from_toml("heading = \"[dependencies]\"").is_err());
+}
+
+#[test]
+fn sort_key_orders() {
+    use std::cmp::Ordering::*;
+
+    assert_eq!(KeyOrder::Lexical.compare("foo10", "foo2"), Less);
+    assert_eq!(KeyOrder::Natural.compare("foo10", "foo2"), Greater);
+    assert_eq!(KeyOrder::Natural.compare("foo02", "foo2"), Equal);
+    assert_eq!(KeyOrder::Lexical.compare("B", "a"), Less);
+    assert_eq!(KeyOrder::CaseInsensitive.compare("B", "a"), Greater);
+    assert_eq!(KeyOrder::CaseInsensitive.compare("\"B\"", "a"), Greater);
+
+    let priority = KeyOrder::Priority(vec!["name".into(), "version".into()]);
+    assert_eq!(priority.compare("version", "name"), Greater);
+    assert_eq!(priority.compare("authors", "version"), Greater);
+    assert_eq!(priority.compare("authors", "edition"), Less);
+}
+
+#[test]
+fn sort_config_key_order() {
+    let file = r#"[package]
+authors = [ "a" ]
+version = "0.1.0"
+edition = "2018"
+name = "a"
+
+[dependencies]
+serde10 = "1"
+Serde2 = "1"
+serde1 = "1"
+"#;
+    let config = SortConfig::from_toml(
+        r#"heading = [ "[package]", "[dependencies]" ]
+
+[[key_order]]
+heading = "exact:[package]"
+order = "priority"
+priority = [ "name", "version", "edition" ]
+
+[[key_order]]
+heading = "[dependencies]"
+order = "natural"
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config,
+        SortConfig::builder()
+            .heading("[package]")
+            .heading("[dependencies]")
+            .key_order(
+                "exact:[package]",
+                KeyOrder::Priority(vec!["name".into(), "version".into(), "edition".into()])
+            )
+            .key_order("[dependencies]", KeyOrder::Natural)
+            .build()
+            .unwrap()
+    );
+
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let sorted = config.sort(&parsed).token_text();
+    assert!(in_order(
+        &sorted,
+        &["name", "version", "edition", "authors", "Serde2", "serde1", "serde10"]
+    ));
+
+    let lexical = SortConfig::builder()
+        .heading("[dependencies]")
+        .build()
+        .unwrap()
+        .sort(&parsed)
+        .token_text();
+    assert!(in_order(&lexical, &["Serde2", "serde1", "serde10"]));
+
+    assert!(
+        SortConfig::from_toml("[[key_order]]\nheading = \"[package]\"\norder = \"random\"\n")
+            .is_err()
+    );
+}
+
+#[test]
+fn sort_table_order() {
+    let input = read_to_string("examp/table_order.toml").expect("file read failed");
+    let expected = read_to_string("examp/table_order.fix.toml").expect("file read failed");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+
+    let ordered = order_tables(&parsed, CARGO_TABLE_ORDER);
+    assert_eq!(ordered.token_text(), expected);
+    // the tree has the shape the parser gives it
+    let reparsed = parse_it(&expected).expect("parse failed").syntax();
+    assert!(ordered.deep_eq(&reparsed));
+
+    // already in order
+    assert!(order_tables(&reparsed, CARGO_TABLE_ORDER).deep_eq(&reparsed));
+
+    let config = SortConfig::from_toml(
+        r#"heading = [ "[dependencies]" ]
+table_order = [ "exact:[package]", "exact:[[bin]]", "exact:[dependencies]", "exact:[dev-dependencies]", "exact:[workspace]" ]
+"#,
+    )
+    .unwrap();
+    let sorted = config.sort(&parsed).token_text();
+    assert!(sorted.find("[package]").unwrap() < sorted.find("[[bin]]").unwrap());
+    assert!(sorted.find("[[bin]]").unwrap() < sorted.find("[dependencies]").unwrap());
+}
+
+#[test]
+fn sort_check_violations() {
+    let file = r#"[workspace]
+members = [ "b", "c", "a" ]
+
+[dependencies]
+serde = "1"
+chrono = "0.4"
+rowan = "0.10"
+
+[dependencies.b]
+version = "1"
+
+[dependencies.a]
+version = "1"
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let found = check_sorted(&parsed, &HEADER)
+        .into_iter()
+        .map(|v| (v.kind, &file[v.range], v.found, v.expected))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        vec![
+            (ViolationKind::ArrayItem, "\"a\"", 2, 0),
+            (ViolationKind::Key, "serde = \"1\"", 0, 2),
+            (
+                ViolationKind::Table,
+                "[dependencies.b]\nversion = \"1\"",
+                0,
+                1
+            ),
+        ]
+    );
+
+    let sorted = sort_toml_items(&parsed, &HEADER);
+    assert!(check_sorted(&sorted, &HEADER).is_empty());
+
+    let config = SortConfig::builder()
+        .table_order("exact:[package]")
+        .table_order("exact:[dependencies]")
+        .table_order("exact:[workspace]")
+        .build()
+        .unwrap();
+    let violations = config.violations(&parsed);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].kind, ViolationKind::Table);
+    assert!(file[violations[0].range].starts_with("[workspace]"));
+    assert_eq!((violations[0].found, violations[0].expected), (0, 1));
+}
+
+#[test]
+fn sort_check_many_moves() {
+    // far more moves than a diff of the items would search for
+    let mut file = String::from("[dependencies]\n");
+    for i in (0..3000).rev() {
+        file.push_str(&format!("k{:04} = \"1\"\n", i));
+    }
+    file.push_str("z = \"1\"\n");
+    let parsed = parse_it(&file).expect("parse failed").syntax();
+    let violations = check_sorted(&parsed, &HEADER);
+    assert_eq!(violations.len(), 2999);
+    // `k0000` and `z` are already where they go
+    assert!(violations
+        .iter()
+        .all(|v| v.found != 2999 && v.found != 3000));
+    assert_eq!((violations[0].found, violations[0].expected), (0, 2999));
+}
+
+#[test]
+fn sort_inline_table_keys() {
+    let file = r#"[dependencies]
+serde = { version = "1", features = [ "derive" ], default-features = false }
+rowan = {version="0.10",optional=true,}
+"#;
+    const INLINE: Matcher<'static> = Matcher {
+        heading: &["[dependencies]"],
+        segmented: &[],
+        heading_key: &[("[dependencies]", "glob:*")],
+        array_table_key: &[],
+    };
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    assert_eq!(check_sorted(&parsed, &INLINE).len(), 4);
+
+    let sorted = sort_toml_items(&parsed, &INLINE);
+    assert_eq!(
+        sorted.token_text(),
+        r#"[dependencies]
+rowan = {optional=true,version="0.10",}
+serde = { default-features = false, features = [ "derive" ], version = "1" }
+"#
+    );
+    assert!(check_sorted(&sorted, &INLINE).is_empty());
+
+    // without a `heading_key` inline tables are copied as they are
+    let sorted = sort_toml_items(&parsed, &HEADER);
+    assert!(sorted
+        .token_text()
+        .contains("{version=\"0.10\",optional=true,}"));
+}
+
+#[test]
+fn sort_array_values() {
+    let file = r#"[workspace]
+members = [
+    # the parser
+    "tkn", # tokens
+    "Fmt",
+    "diff",
+    "fmt",
+]
+ports = [ 8080, 443, 80 ]
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let config = SortConfig::builder()
+        .heading_key("[workspace]", "glob:*")
+        .build()
+        .unwrap();
+    let sorted = config.sort(&parsed).token_text();
+    assert_eq!(
+        sorted,
+        r#"[workspace]
+members = [
+    "Fmt",
+    "diff",
+    "fmt",
+    # the parser
+    "tkn", # tokens
+]
+ports = [ 80, 443, 8080 ]
+"#
+    );
+    assert!(config
+        .violations(&parse_it(&sorted).unwrap().syntax())
+        .is_empty());
+
+    let config = SortConfig::builder()
+        .heading_key("[workspace]", "members")
+        .array_order(
+            "[workspace]",
+            ArrayOrder {
+                case_insensitive: true,
+                dedup: true,
+            },
+        )
+        .build()
+        .unwrap();
+    let sorted = config.sort(&parsed).token_text();
+    assert!(sorted.starts_with(
+        r#"[workspace]
+members = [
+    "diff",
+    "Fmt",
+    # the parser
+    "tkn", # tokens
+]
+"#
+    ));
+
+    let from_file = SortConfig::from_toml(
+        r#"[[heading_key]]
+heading = "[workspace]"
+key = "members"
+
+[[array_order]]
+heading = "[workspace]"
+case_insensitive = true
+dedup = true
+"#,
+    )
+    .unwrap();
+    assert_eq!(from_file, config);
+}
+
+#[test]
+fn sort_check_dedup() {
+    let file = "[workspace]\nmembers = [\"a\", \"A\", \"b\"]\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let config = SortConfig::builder()
+        .heading_key("[workspace]", "members")
+        .array_order(
+            "[workspace]",
+            ArrayOrder {
+                case_insensitive: true,
+                dedup: true,
+            },
+        )
+        .build()
+        .unwrap();
+    let found = config
+        .violations(&parsed)
+        .into_iter()
+        .map(|v| (v.kind, &file[v.range], v.found, v.expected))
+        .collect::<Vec<_>>();
+    assert_eq!(found, vec![(ViolationKind::ArrayItem, "\"A\"", 1, 0)]);
+
+    let sorted = config.sort(&parsed).token_text();
+    assert_eq!(sorted, "[workspace]\nmembers = [\"a\", \"b\"]\n");
+    assert!(config
+        .violations(&parse_it(&sorted).unwrap().syntax())
+        .is_empty());
+}
+
+#[test]
+fn sort_array_tables_by_key() {
+    let file = r#"[package]
+name = "app"
+
+# the web server
+[[services]]
+name = "web"
+port = 80
+
+[services.env]
+LOG = "info"
+
+[[services]]
+name = "db"
+
+[[services]]
+port = 9000
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let sorted = sort_array_tables(&parsed, "[[services]]", "name");
+    assert_eq!(
+        sorted.token_text(),
+        r#"[package]
+name = "app"
+
+[[services]]
+name = "db"
+
+# the web server
+[[services]]
+name = "web"
+port = 80
+
+[services.env]
+LOG = "info"
+
+[[services]]
+port = 9000
+"#
+    );
+
+    let config = SortConfig::from_toml(
+        r#"[[array_table_key]]
+heading = "glob:[[*]]"
+key = "name"
+"#,
+    )
+    .unwrap();
+    let violations = config.violations(&parsed);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].kind, ViolationKind::Table);
+    assert!(file[violations[0].range].starts_with("[[services]]\nname = \"web\""));
+    assert_eq!((violations[0].found, violations[0].expected), (0, 1));
+    assert_eq!(config.sort(&parsed).token_text(), sorted.token_text());
+    assert!(config.violations(&sorted).is_empty());
+
+    const ENTRIES: Matcher<'static> = Matcher {
+        heading: &[],
+        segmented: &[],
+        heading_key: &[],
+        array_table_key: &[("[[services]]", "name")],
+    };
+    assert_eq!(
+        sort_toml_items(&parsed, &ENTRIES).token_text(),
+        sorted.token_text()
+    );
+    assert_eq!(check_sorted(&parsed, &ENTRIES), violations);
+}
+
+#[test]
+fn sort_key_groups() {
+    let file = r#"[dependencies]
+# async runtime
+tokio = "1"
+futures = "0.3" # executors
+# serialization
+serde_json = "1"
+serde = "1"
+
+rowan = "0.10"
+chrono = "0.4"
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let config = SortConfig::builder()
+        .heading("[dependencies]")
+        .groups(true)
+        .build()
+        .unwrap();
+    let violations = config.violations(&parsed);
+    assert_eq!(violations.len(), 3);
+
+    let sorted = config.sort(&parsed).token_text();
+    assert_eq!(
+        sorted,
+        r#"[dependencies]
+# async runtime
+futures = "0.3" # executors
+tokio = "1"
+# serialization
+serde = "1"
+serde_json = "1"
+
+chrono = "0.4"
+rowan = "0.10"
+"#
+    );
+    assert!(config
+        .violations(&parse_it(&sorted).unwrap().syntax())
+        .is_empty());
+
+    let from_file =
+        SortConfig::from_toml("heading = [ \"[dependencies]\" ]\ngroups = true\n").unwrap();
+    assert_eq!(from_file, config);
+}
+
+#[test]
+fn sort_segmented_hierarchy() {
+    let file = r#"[dependencies.b]
+version = "1"
+
+[dependencies.a.features]
+x = true
+
+[dependencies.c.target.inner]
+y = 1
+
+[dependencies.a]
+version = "1"
+
+[dependencies.c.other]
+z = 1
+
+[dev-dependencies.a]
+version = "1"
+"#;
+    const SEG: Matcher<'static> = Matcher {
+        heading: &[],
+        segmented: &["dependencies.", "dev-dependencies."],
+        heading_key: &[],
+        array_table_key: &[],
+    };
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let sorted = sort_toml_items(&parsed, &SEG).token_text();
+    let order = [
+        "[dependencies.a]",
+        "[dependencies.a.features]",
+        "[dependencies.b]",
+        "[dependencies.c.other]",
+        "[dependencies.c.target.inner]",
+        "[dev-dependencies.a]",
+    ];
+    assert!(order
+        .windows(2)
+        .all(|pair| sorted.find(pair[0]).unwrap() < sorted.find(pair[1]).unwrap()));
+    assert!(check_sorted(&parse_it(&sorted).unwrap().syntax(), &SEG).is_empty());
+}
+
+#[test]
+fn sort_dotted_keys() {
+    let file = r#"[package]
+b = 1
+a.c = 2
+a-x = 3
+"a".b = 4
+metadata.docs.rs = true
+a.d = 5
+metadata.b = 6
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let config = SortConfig::builder().heading("[package]").build().unwrap();
+    let sorted = config.sort(&parsed).token_text();
+    assert_eq!(
+        sorted,
+        r#"[package]
+"a".b = 4
+a.c = 2
+a.d = 5
+a-x = 3
+b = 1
+metadata.b = 6
+metadata.docs.rs = true
+"#
+    );
+    assert_eq!(config.violations(&parsed).len(), 4);
+    assert!(config
+        .violations(&parse_it(&sorted).unwrap().syntax())
+        .is_empty());
+}
+
+#[test]
+fn sort_in_place_shares_unchanged() {
+    let file = r#"[package]
+name = "a"
+
+[dependencies]
+b = "1"
+a = "1"
+c = "1"
+
+[workspace]
+members = ["a", "b"]
+"#;
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    let sorted = sort_in_place(&parsed, &HEADER);
+    let text = sorted.root.token_text();
+    assert_eq!(sorted.touched.len(), 1);
+    assert_eq!(
+        &text[sorted.touched[0]],
+        "[dependencies]\na = \"1\"\nb = \"1\"\nc = \"1\"\n\n"
+    );
+
+    let tables = |root: &SyntaxNode| root.children().collect::<Vec<_>>();
+    let (old, new) = (tables(&parsed), tables(&sorted.root));
+    assert_eq!(old[0].green(), new[0].green());
+    assert_eq!(old[2].green(), new[2].green());
+    assert_ne!(old[1].green(), new[1].green());
+    assert_eq!(text, sort_toml_items(&parsed, &HEADER).token_text());
+
+    let again = sort_in_place(&sorted.root, &HEADER);
+    assert!(again.touched.is_empty());
+    assert!(again.root == sorted.root);
+}
+
+#[test]
+fn sort_segmented_runs() {
+    // `[hello]` ends a run, `three.last` and `two.alpha` are in order by
+    // their full path and so are `four.gama` and `one.beta`
+    let input = read_to_string("examp/seg_sort.toml").expect("file read failed");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+    assert!(parsed.deep_eq(&sort_toml_items(&parsed, &HEADER)));
+    assert!(check_sorted(&parsed, &HEADER).is_empty());
+
+    let file = "[dependencies.b]\nv = 1\n\n[dependencies.a]\nv = 1\n\n[hello]\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    assert_eq!(
+        sort_toml_items(&parsed, &HEADER).token_text(),
+        "[dependencies.a]\nv = 1\n\n[dependencies.b]\nv = 1\n\n[hello]\n"
+    );
+    assert_eq!(check_sorted(&parsed, &HEADER).len(), 1);
+}
+
+#[test]
+fn sort_segmented_run_limit() {
+    // siblings split by a table that does not match are not ordered across it
+    let file = "[dependencies.b]\nv = 1\n\n[hello]\n\n[dependencies.a]\nv = 1\n";
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    assert_eq!(sort_toml_items(&parsed, &HEADER).token_text(), file);
+    assert!(check_sorted(&parsed, &HEADER).is_empty());
+
+    let config = SortConfig::builder()
+        .segmented("dependencies.")
+        .build()
+        .unwrap();
+    assert_eq!(config.sort(&parsed).token_text(), file);
+    assert!(config.violations(&parsed).is_empty());
+}
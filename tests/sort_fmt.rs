@@ -14,6 +14,7 @@ const MATCHER: Matcher<'_> = Matcher {
     heading: &HEADERS,
     segmented: &HEADER_SEG,
     heading_key: &[("[workspace]", "members"), ("[workspace]", "exclude")],
+    array_table_key: &[],
 };
 
 #[test]
@@ -47,10 +48,27 @@ fn sort_fmt_seg_sort() {
 
     let sorted = sort_toml_items(&parsed, &MATCHER);
 
+    // each run of segmented tables is already in order
+    assert!(parsed.deep_eq(&sorted));
+    assert_eq!(sorted.text_range(), parsed.text_range());
+
+    assert_eq!(input, Formatter::new(&sorted).format().to_string())
+}
+
+#[test]
+fn sort_fmt_seg_sort_path() {
+    let input = read_to_string("examp/seg_sort_path.toml").expect("file read failed");
+    let parsed = parse_it(&input).expect("parse failed").syntax();
+
+    let sorted = sort_toml_items(&parsed, &MATCHER);
+
     assert!(!parsed.deep_eq(&sorted));
     assert_eq!(sorted.text_range(), parsed.text_range());
 
-    assert_ne!(input, Formatter::new(&sorted).format().to_string())
+    assert_eq!(
+        "[dependencies.three.last]\nversion = \"0.15\"\n\n[dependencies.two.alpha]\nversion = \"0.15\"\n\n[hello]\n\n[dependencies.four.gama]\nversion = \"0.15\"\n\n[dependencies.one.beta]\nversion = \"0.15\"\n\n",
+        Formatter::new(&sorted).format().to_string()
+    )
 }
 
 #[test]
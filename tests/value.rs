@@ -0,0 +1,133 @@
+use toml_parse::{parse_it, SyntaxNode, TomlDate, TomlErrorKind, TomlKind, ValueExtTrait};
+
+fn values(file: &str) -> Vec<SyntaxNode> {
+    let parsed = parse_it(file).expect("parse failed").syntax();
+    parsed
+        .descendants()
+        .filter(|n| n.kind() == TomlKind::Value && n.parent().unwrap().kind() == TomlKind::KeyValue)
+        .collect()
+}
+
+#[test]
+fn value_scalars() {
+    let file = r#"[table]
+yes = true
+num = 1234
+float = 12.5
+"#;
+    let vals = values(file);
+    assert_eq!(vals[0].as_bool(), Ok(true));
+    assert_eq!(vals[1].as_i64(), Ok(1234));
+    assert_eq!(vals[2].as_f64(), Ok(12.5));
+}
+
+#[test]
+fn value_integer_prefixes() {
+    let file = "hex = 0xdead_beef\noct = 0o755\nbin = 0b1101\nsep = 1_000\n";
+    let vals = values(file);
+    assert_eq!(vals[0].as_i64(), Ok(0xdead_beef));
+    assert_eq!(vals[1].as_i64(), Ok(0o755));
+    assert_eq!(vals[2].as_i64(), Ok(0b1101));
+    assert_eq!(vals[3].as_i64(), Ok(1000));
+}
+
+#[test]
+fn value_integer_bounds() {
+    let file = "min = -9223372036854775808\nmax = +9223372036854775807\n\
+                over = 9223372036854775808\nneg = -1.5\n";
+    let vals = values(file);
+    assert_eq!(vals[0].as_i64(), Ok(i64::MIN));
+    assert_eq!(vals[1].as_i64(), Ok(i64::MAX));
+    assert!(vals[2].as_i64().is_err());
+    assert_eq!(vals[3].as_f64(), Ok(-1.5));
+}
+
+#[test]
+fn value_strings() {
+    let file = r#"basic = "a\tb"
+literal = 'a\tb'
+multi = """
+line"""
+"#;
+    let vals = values(file);
+    assert_eq!(vals[0].as_str(), Ok("a\tb".to_string()));
+    assert_eq!(vals[1].as_str(), Ok("a\\tb".to_string()));
+    assert_eq!(vals[2].as_str(), Ok("line".to_string()));
+}
+
+#[test]
+fn value_string_escapes() {
+    let file = r#"quote = "say \"hi\" \\ ok"
+uni = "caf\u00e9 \U0001F600"
+ws = "a\nb\rc"
+"#;
+    let vals = values(file);
+    assert_eq!(vals[0].as_str(), Ok("say \"hi\" \\ ok".to_string()));
+    assert_eq!(vals[1].as_str(), Ok("caf\u{e9} \u{1F600}".to_string()));
+    assert_eq!(vals[2].as_str(), Ok("a\nb\rc".to_string()));
+}
+
+#[test]
+fn value_dates() {
+    let file = "date = 1988-02-03\ndt = 1988-02-03T10:32:10\ntime = 10:32:10\n";
+    let vals = values(file);
+    assert!(matches!(vals[0].as_datetime(), Ok(TomlDate::LocalDate(_))));
+    assert!(matches!(
+        vals[1].as_datetime(),
+        Ok(TomlDate::LocalDateTime(_))
+    ));
+    assert!(matches!(vals[2].as_datetime(), Ok(TomlDate::LocalTime(_))));
+}
+
+#[test]
+fn value_offset_datetimes() {
+    let file = "utc = 1979-05-27T07:32:00Z\noff = 1979-05-27T00:32:00-07:00\n";
+    let vals = values(file);
+    let (utc, off) = match (vals[0].as_datetime(), vals[1].as_datetime()) {
+        (Ok(TomlDate::OffsetDateTime(utc)), Ok(TomlDate::OffsetDateTime(off))) => (utc, off),
+        other => panic!("expected offset date times {:?}", other),
+    };
+    // the same instant written with different offsets
+    assert_eq!(utc, off);
+    assert_eq!(off.offset().local_minus_utc(), -7 * 3600);
+}
+
+#[test]
+fn value_array_inline_table() {
+    let file = r#"arr = [ 1, 2, 3 ]
+tab = { a = "b", "c" = false }
+"#;
+    let vals = values(file);
+    let arr = vals[0]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_i64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(arr, vec![1, 2, 3]);
+
+    let tab = vals[1].as_inline_table().unwrap();
+    assert_eq!(tab[0].0, "a");
+    assert_eq!(tab[0].1.as_str(), Ok("b".to_string()));
+    assert_eq!(tab[1].0, "c");
+    assert_eq!(tab[1].1.as_bool(), Ok(false));
+}
+
+#[test]
+fn value_mismatch() {
+    let file = "num = \"1234\"\n";
+    let vals = values(file);
+    let err = vals[0].as_i64().unwrap_err();
+    match err.kind() {
+        TomlErrorKind::TypeMismatch {
+            expected,
+            found,
+            range,
+        } => {
+            assert_eq!(*expected, TomlKind::Integer);
+            assert_eq!(*found, TomlKind::Str);
+            assert_eq!(&file[*range], "\"1234\"");
+        }
+        _ => panic!("expected a type mismatch"),
+    }
+}